@@ -0,0 +1,205 @@
+// cycle finding for any deterministic state machine: give it a starting
+// state, a function that steps to the next state, and a key that's equal
+// exactly when two states are (the state itself, a compact encoding of it,
+// or a hash if you're feeling lucky). uses brent's algorithm, so it only
+// ever holds a couple of states at once instead of remembering everything
+// it's seen. days pull it in with cycle = { path = "../cycle" } in their
+// Cargo.toml.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    // how many steps it takes to reach the first state that's in the loop
+    pub mu: u64,
+    // how many steps it takes to get around the loop
+    pub lambda: u64,
+}
+
+impl Cycle {
+    // the smallest number of steps that ends up in the same state as
+    // taking n steps
+    pub fn reduce(&self, n: u64) -> u64 {
+        if n < self.mu {
+            n
+        } else {
+            self.mu + (n - self.mu) % self.lambda
+        }
+    }
+
+    // the state after n steps, without actually taking all n of them
+    pub fn fast_forward<S, F>(&self, start: &S, step: F, n: u64) -> S
+    where
+        S: Clone,
+        F: Fn(&S) -> S,
+    {
+        step_n(start, &step, self.reduce(n))
+    }
+}
+
+pub fn find_cycle<S, K, F, H>(start: &S, step: F, key: H) -> Cycle
+where
+    S: Clone,
+    F: Fn(&S) -> S,
+    H: Fn(&S) -> K,
+    K: PartialEq,
+{
+    let (_, _, lambda) = find_lambda(start, &step, &key, u64::MAX);
+    let lambda = lambda.unwrap();
+
+    // then walk two states lambda apart forward together until they meet,
+    // which happens at the start of the loop
+    let mut tortoise = start.clone();
+    let mut hare = step_n(start, &step, lambda);
+    let mut mu: u64 = 0;
+    while key(&tortoise) != key(&hare) {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        mu += 1;
+    }
+    Cycle { mu, lambda }
+}
+
+// the state after n steps. stops as soon as it either gets there or finds
+// the loop, so it never takes more than n steps, and never bothers working
+// out where the loop starts
+pub fn nth_state<S, K, F, H>(start: &S, step: F, key: H, n: u64) -> S
+where
+    S: Clone,
+    F: Fn(&S) -> S,
+    H: Fn(&S) -> K,
+    K: PartialEq,
+{
+    match find_lambda(start, &step, &key, n) {
+        (hare, taken, Some(lambda)) => step_n(&hare, &step, (n - taken) % lambda),
+        (hare, _, None) => hare,
+    }
+}
+
+// finds lambda by moving the tortoise up to the hare at every power of two
+// until the hare runs into it, giving up once the hare has taken `limit`
+// steps. returns the hare, how many steps it took and lambda if it got
+// that far, in which case the hare is already somewhere in the loop
+fn find_lambda<S, K, F, H>(start: &S, step: &F, key: &H, limit: u64) -> (S, u64, Option<u64>)
+where
+    S: Clone,
+    F: Fn(&S) -> S,
+    H: Fn(&S) -> K,
+    K: PartialEq,
+{
+    if limit == 0 {
+        return (start.clone(), 0, None);
+    }
+    let mut power: u64 = 1;
+    let mut lambda: u64 = 1;
+    let mut tortoise_key = key(start);
+    let mut hare = step(start);
+    let mut hare_key = key(&hare);
+    let mut taken: u64 = 1;
+    while tortoise_key != hare_key {
+        if taken == limit {
+            return (hare, taken, None);
+        }
+        if power == lambda {
+            tortoise_key = hare_key;
+            power *= 2;
+            lambda = 0;
+        }
+        hare = step(&hare);
+        hare_key = key(&hare);
+        lambda += 1;
+        taken += 1;
+    }
+    (hare, taken, Some(lambda))
+}
+
+fn step_n<S, F>(start: &S, step: &F, n: u64) -> S
+where
+    S: Clone,
+    F: Fn(&S) -> S,
+{
+    let mut state = start.clone();
+    for _ in 0..n {
+        state = step(&state);
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // x -> x^2 + 1 mod m wanders for a bit and then goes round in a loop
+    fn rho(m: u64) -> impl Fn(&u64) -> u64 {
+        move |&x| (x * x + 1) % m
+    }
+
+    // remembers every state to find mu and lambda the obvious way
+    fn find_cycle_with_map(start: u64, m: u64) -> Cycle {
+        let step = rho(m);
+        let mut seen: HashMap<u64, u64> = HashMap::new();
+        let mut x = start;
+        let mut i = 0;
+        while !seen.contains_key(&x) {
+            seen.insert(x, i);
+            x = step(&x);
+            i += 1;
+        }
+        let mu = seen[&x];
+        Cycle { mu, lambda: i - mu }
+    }
+
+    #[test]
+    fn matches_map() {
+        for m in 1..200 {
+            for start in 0..m {
+                assert_eq!(
+                    find_cycle(&start, rho(m), |&x| x),
+                    find_cycle_with_map(start, m),
+                    "start {} mod {}",
+                    start,
+                    m
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn known_rho() {
+        // 0, 1, 2, 5, then 26, 677, 330, 901, 802, 205 over and over
+        let cycle = find_cycle(&0, rho(1000), |&x| x);
+        assert_eq!(cycle, Cycle { mu: 4, lambda: 6 });
+        assert_eq!(nth_state(&0, rho(1000), |&x| x, 3), 5);
+        assert_eq!(nth_state(&0, rho(1000), |&x| x, 4 + 6), 26);
+        assert_eq!(
+            nth_state(&0, rho(1000), |&x| x, 4 + 6 * 1_000_000_000 + 5),
+            205
+        );
+        assert_eq!(cycle.reduce(0), 0);
+        assert_eq!(cycle.reduce(cycle.mu), cycle.mu);
+        assert_eq!(cycle.reduce(cycle.mu + cycle.lambda), cycle.mu);
+        assert_eq!(cycle.reduce(cycle.mu + 2 * cycle.lambda + 1), cycle.mu + 1);
+    }
+
+    #[test]
+    fn skips_ahead() {
+        let m = 1000;
+        for start in [0, 3, 17, 999] {
+            let cycle = find_cycle_with_map(start, m);
+            let (mu, lambda) = (cycle.mu, cycle.lambda);
+            assert!(mu > 1, "want a tail to step along");
+            let big = 1_000_000_000_000_000_007;
+            for n in [0, 1, mu - 1, mu, mu + lambda, mu + lambda + 1, 1000, big] {
+                let expected = step_n(&start, &rho(m), cycle.reduce(n));
+                assert_eq!(nth_state(&start, rho(m), |&x| x, n), expected, "n = {}", n);
+                assert_eq!(cycle.fast_forward(&start, rho(m), n), expected, "n = {}", n);
+            }
+            // stepping the slow way agrees for anything small enough to do it
+            for n in 0..3 * (mu + lambda) {
+                assert_eq!(
+                    nth_state(&start, rho(m), |&x| x, n),
+                    step_n(&start, &rho(m), n)
+                );
+            }
+        }
+    }
+}
@@ -1,14 +1,21 @@
 use std::collections::HashSet;
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::process;
 use std::time::Instant;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let file = &args[1];
+    if args.get(2).is_some_and(|a| a == "--validate") {
+        validate(file);
+        return;
+    }
+
     let start1 = Instant::now();
     let p1 = part1(file);
     let duration1 = start1.elapsed();
@@ -20,102 +27,292 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-// return the grid, starting coord, and set of all points in the main loop
-fn parse_grid(file: &str) -> (Vec<Vec<char>>, bool, HashSet<(usize, usize)>) {
+const NORTH: (i32, i32) = (-1, 0);
+const SOUTH: (i32, i32) = (1, 0);
+const WEST: (i32, i32) = (0, -1);
+const EAST: (i32, i32) = (0, 1);
+
+const PIPES: [(char, [(i32, i32); 2]); 6] = [
+    ('|', [NORTH, SOUTH]),
+    ('-', [WEST, EAST]),
+    ('L', [NORTH, EAST]),
+    ('J', [NORTH, WEST]),
+    ('7', [SOUTH, WEST]),
+    ('F', [SOUTH, EAST]),
+];
+
+// the two directions a pipe leads in, or None if it's not a pipe
+fn pipe_connections(c: char) -> Option<[(i32, i32); 2]> {
+    PIPES.iter().find(|(p, _)| *p == c).map(|(_, dirs)| *dirs)
+}
+
+fn step(grid: &[Vec<char>], pos: (usize, usize), dir: (i32, i32)) -> Option<(usize, usize)> {
+    let row = pos.0 as i32 + dir.0;
+    let col = pos.1 as i32 + dir.1;
+    if row < 0 || col < 0 || row as usize >= grid.len() || col as usize >= grid[0].len() {
+        return None;
+    }
+    Some((row as usize, col as usize))
+}
+
+// which neighbours of `pos` have a pipe pointing back at it
+fn connected_neighbours(grid: &[Vec<char>], pos: (usize, usize)) -> Vec<(i32, i32)> {
+    [NORTH, SOUTH, WEST, EAST]
+        .into_iter()
+        .filter(|&dir| {
+            step(grid, pos, dir)
+                .and_then(|(r, c)| pipe_connections(grid[r][c]))
+                .is_some_and(|dirs| dirs.contains(&(-dir.0, -dir.1)))
+        })
+        .collect()
+}
+
+// the pipe hiding under S, worked out from which neighbours point back at it
+fn infer_start_pipe(grid: &[Vec<char>], start: (usize, usize)) -> Result<char, MazeError> {
+    let connected = connected_neighbours(grid, start);
+    PIPES
+        .iter()
+        .find(|(_, dirs)| connected.len() == 2 && connected.iter().all(|d| dirs.contains(d)))
+        .map(|(p, _)| *p)
+        .ok_or(MazeError::AmbiguousStart {
+            pos: start,
+            connected,
+        })
+}
+
+#[derive(Debug)]
+enum MazeError {
+    UnexpectedChar {
+        pos: (usize, usize),
+        c: char,
+    },
+    RaggedRow {
+        row: usize,
+    },
+    NoStart,
+    MultipleStarts(Vec<(usize, usize)>),
+    // S needs exactly two neighbours pointing at it to know what pipe it is
+    AmbiguousStart {
+        pos: (usize, usize),
+        connected: Vec<(i32, i32)>,
+    },
+    // following the loop from S led to a pipe that goes nowhere
+    BrokenLoop {
+        pos: (usize, usize),
+    },
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MazeError::UnexpectedChar { pos, c } => write!(f, "unexpected char {} at {:?}", c, pos),
+            MazeError::RaggedRow { row } => write!(f, "row {} is a different length", row),
+            MazeError::NoStart => write!(f, "no S in the maze"),
+            MazeError::MultipleStarts(starts) => write!(f, "more than one S: {:?}", starts),
+            MazeError::AmbiguousStart { pos, connected } => write!(
+                f,
+                "S at {:?} has {} connected neighbours {:?}, expected 2",
+                pos,
+                connected.len(),
+                connected
+            ),
+            MazeError::BrokenLoop { pos } => write!(f, "loop from S breaks at {:?}", pos),
+        }
+    }
+}
+
+struct Maze {
+    // the grid with S swapped out for the pipe it's sitting on
+    grid: Vec<Vec<char>>,
+    // every tile of the main loop in order, starting at S
+    loop_path: Vec<(usize, usize)>,
+}
+
+fn parse_grid(file: &str) -> Result<Maze, MazeError> {
+    maze_from_lines(read_lines(file).unwrap().map(|l| l.unwrap()))
+}
+
+fn maze_from_lines(lines: impl Iterator<Item = String>) -> Result<Maze, MazeError> {
     let mut grid: Vec<Vec<char>> = Vec::new();
-    let mut start: (usize, usize) = (usize::MAX, usize::MAX);
-    for l in read_lines(file).unwrap() {
-        let line = l.unwrap();
-        if let Some(start_col) = line.find('S') {
-            start = (grid.len(), start_col);
+    let mut starts: Vec<(usize, usize)> = Vec::new();
+    for line in lines {
+        let row: Vec<char> = line.chars().collect();
+        for (col, &c) in row.iter().enumerate() {
+            if c == 'S' {
+                starts.push((grid.len(), col));
+            } else if c != '.' && pipe_connections(c).is_none() {
+                return Err(MazeError::UnexpectedChar {
+                    pos: (grid.len(), col),
+                    c,
+                });
+            }
+        }
+        if grid.first().is_some_and(|first| first.len() != row.len()) {
+            return Err(MazeError::RaggedRow { row: grid.len() });
         }
-        grid.push(line.chars().collect());
+        grid.push(row);
     }
 
-    let candidate_next: Vec<(i32, i32, Vec<char>)> = vec![
-        // north
-        (-1, 0, vec!['|', '7', 'F']),
-        // west
-        (0, -1, vec!['-', 'L', 'F']),
-        // east
-        (0, 1, vec!['-', 'J', '7']),
-        // south
-        (1, 0, vec!['|', 'L', 'J']),
-    ];
+    let start = match starts[..] {
+        [] => return Err(MazeError::NoStart),
+        [start] => start,
+        _ => return Err(MazeError::MultipleStarts(starts)),
+    };
+    grid[start.0][start.1] = infer_start_pipe(&grid, start)?;
 
-    let mut next_i: Vec<(usize, usize)> = Vec::new();
-    for candidate in candidate_next {
-        let candidate_row = candidate.0 + start.0 as i32;
-        let candidate_col = candidate.1 + start.1 as i32;
-        if candidate_row >= 0
-            && (candidate_row as usize) < grid.len()
-            && candidate_col >= 0
-            && (candidate_col as usize) < grid[0].len()
-            && candidate
-                .2
-                .contains(&grid[candidate_row as usize][candidate_col as usize])
-        {
-            next_i.push((candidate_row as usize, candidate_col as usize));
+    let mut loop_path = vec![start];
+    let mut dir = pipe_connections(grid[start.0][start.1]).unwrap()[0];
+    let mut pos = step(&grid, start, dir).unwrap();
+    while pos != start {
+        // every pipe along the way has to lead somewhere that leads back,
+        // and a loop can't be longer than the whole grid
+        let broken = MazeError::BrokenLoop { pos };
+        if loop_path.len() > grid.len() * grid[0].len() {
+            return Err(broken);
         }
+        loop_path.push(pos);
+        // head out whichever end of this pipe we didn't come in through
+        let dirs = pipe_connections(grid[pos.0][pos.1]).ok_or(broken)?;
+        dir = if dirs[0] == (-dir.0, -dir.1) {
+            dirs[1]
+        } else if dirs[1] == (-dir.0, -dir.1) {
+            dirs[0]
+        } else {
+            return Err(MazeError::BrokenLoop { pos });
+        };
+        pos = step(&grid, pos, dir).ok_or(MazeError::BrokenLoop { pos })?;
     }
+    Ok(Maze { grid, loop_path })
+}
 
-    let mut loop_points: HashSet<(usize, usize)> = HashSet::new();
-    loop_points.insert(start);
-    loop_points.insert(next_i[0]);
-    loop_points.insert(next_i[1]);
-
-    let start_is_corner = next_i == vec![(start.0 - 1, start.1), (start.0, start.1 - 1)]
-        || next_i == vec![(start.0, start.1 + 1), (start.0 + 1, start.1)];
-
-    let mut last_i = vec![start, start];
-
-    while next_i[0] != next_i[1] {
-        for i in 0..next_i.len() {
-            let (row, col) = next_i[i];
-            let grid_char = grid[row][col];
-            let connected: ((usize, usize), (usize, usize)) = match grid_char {
-                '|' => ((row - 1, col), (row + 1, col)),
-                '-' => ((row, col - 1), (row, col + 1)),
-                'L' => ((row - 1, col), (row, col + 1)),
-                'J' => ((row - 1, col), (row, col - 1)),
-                '7' => ((row, col - 1), (row + 1, col)),
-                'F' => ((row, col + 1), (row + 1, col)),
-                _ => panic!("unexpected char"),
+fn load_maze(file: &str) -> Maze {
+    parse_grid(file).unwrap_or_else(|e| {
+        eprintln!("bad maze: {}", e);
+        process::exit(1);
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum TileClass {
+    // part of the main loop
+    InLoop,
+    // not in the loop, and at least one end leads off the grid or into
+    // something that doesn't lead back
+    Dangling,
+    // not in the loop, but connected up at both ends
+    Junk,
+}
+
+// every pipe tile in the maze and what it's doing there
+fn classify_tiles(maze: &Maze) -> Vec<((usize, usize), TileClass)> {
+    let grid = &maze.grid;
+    let loop_points: HashSet<(usize, usize)> = maze.loop_path.iter().copied().collect();
+    let mut tiles = Vec::new();
+    for (row, line) in grid.iter().enumerate() {
+        for (col, &c) in line.iter().enumerate() {
+            let Some(dirs) = pipe_connections(c) else {
+                continue;
             };
-            let next_step = if last_i[i] == connected.0 {
-                connected.1
+            let pos = (row, col);
+            let class = if loop_points.contains(&pos) {
+                TileClass::InLoop
+            } else if dirs
+                .iter()
+                .all(|d| connected_neighbours(grid, pos).contains(d))
+            {
+                TileClass::Junk
             } else {
-                connected.0
+                TileClass::Dangling
             };
-            last_i[i] = next_i[i];
-            next_i[i] = next_step;
-            loop_points.insert(next_step);
+            tiles.push((pos, class));
         }
     }
-    (grid, start_is_corner, loop_points)
+    tiles
+}
+
+// loop tiles that some other pipe outside the loop points into
+fn find_branches(maze: &Maze) -> Vec<(usize, usize)> {
+    let loop_points: HashSet<(usize, usize)> = maze.loop_path.iter().copied().collect();
+    let mut branches: Vec<(usize, usize)> = maze
+        .loop_path
+        .iter()
+        .copied()
+        .filter(|&pos| {
+            connected_neighbours(&maze.grid, pos)
+                .into_iter()
+                .filter_map(|dir| step(&maze.grid, pos, dir))
+                .any(|n| !loop_points.contains(&n))
+        })
+        .collect();
+    branches.sort();
+    branches
+}
+
+fn validate(file: &str) {
+    let maze = match parse_grid(file) {
+        Ok(maze) => maze,
+        Err(e) => {
+            println!("invalid: {}", e);
+            return;
+        }
+    };
+    let tiles = classify_tiles(&maze);
+    for class in [TileClass::InLoop, TileClass::Dangling, TileClass::Junk] {
+        let positions: Vec<(usize, usize)> = tiles
+            .iter()
+            .filter(|(_, c)| *c == class)
+            .map(|(p, _)| *p)
+            .collect();
+        if class == TileClass::Dangling {
+            println!("{:?}: {} {:?}", class, positions.len(), positions);
+        } else {
+            println!("{:?}: {}", class, positions.len());
+        }
+    }
+    println!("branches off the loop: {:?}", find_branches(&maze));
+    println!(
+        "enclosed tiles: {} by shoelace, {} by raycast",
+        enclosed_by_shoelace(&maze.loop_path),
+        enclosed_by_raycast(&maze)
+    );
 }
 
 fn part1(file: &str) -> u32 {
-    let (_, _, loop_points) = parse_grid(file);
+    let maze = load_maze(file);
 
-    loop_points.len() as u32 / 2
+    maze.loop_path.len() as u32 / 2
+}
+
+// the loop is a polygon with a vertex at every tile, so the shoelace formula
+// gives its area and pick's theorem (A = i + b/2 - 1) turns that into the
+// number of tiles strictly inside it
+fn enclosed_by_shoelace(loop_path: &[(usize, usize)]) -> u32 {
+    let mut twice_area: i64 = 0;
+    for (i, &(r1, c1)) in loop_path.iter().enumerate() {
+        let (r2, c2) = loop_path[(i + 1) % loop_path.len()];
+        twice_area += c1 as i64 * r2 as i64 - c2 as i64 * r1 as i64;
+    }
+    let boundary = loop_path.len() as i64;
+    (twice_area.abs() / 2 - boundary / 2 + 1) as u32
 }
 
 fn part2(file: &str) -> u32 {
-    let (grid, start_is_corner, loop_points) = parse_grid(file);
+    let maze = load_maze(file);
+    let count = enclosed_by_shoelace(&maze.loop_path);
+    debug_assert_eq!(count, enclosed_by_raycast(&maze));
+    count
+}
 
-    let mut irrelevant_edges = vec!['F', 'J'];
-    if start_is_corner {
-        // man screw this edge case
-        irrelevant_edges.push('S');
-    }
+fn enclosed_by_raycast(maze: &Maze) -> u32 {
+    let grid = &maze.grid;
+    let loop_points: HashSet<(usize, usize)> = maze.loop_path.iter().copied().collect();
 
     // skip top left and bottom right corners since our ray will just
     // be glancing off those
     let relevant_edges: HashSet<(usize, usize)> = loop_points
         .iter()
-        .filter(|&p| !irrelevant_edges.contains(&grid[p.0][p.1]))
-        .map(|p| *p)
+        .filter(|&p| !['F', 'J'].contains(&grid[p.0][p.1]))
+        .copied()
         .collect();
 
     // raycasting with diagonal lines because I wasted enough time trying to figure out
@@ -131,9 +328,7 @@ fn part2(file: &str) -> u32 {
         while cur_col < grid[0].len() {
             if relevant_edges.contains(&(cur_row, cur_col)) {
                 in_loop = !in_loop;
-                //println!("toggling loop: {}, {}, {}", cur_row, cur_col, in_loop);
             } else if in_loop && !loop_points.contains(&(cur_row, cur_col)) {
-                //println!("in loop: {}, {}", cur_row, cur_col);
                 count += 1;
             }
 
@@ -161,3 +356,75 @@ where
     let file = File::open(filename)?;
     Ok(io::BufReader::new(file).lines())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maze(text: &str) -> Maze {
+        maze_from_lines(text.lines().map(|l| l.to_string())).unwrap()
+    }
+
+    // the part2 examples from the puzzle
+    const EXAMPLES: [(&str, u32); 4] = [
+        (
+            "...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........",
+            4,
+        ),
+        (
+            "..........
+.S------7.
+.|F----7|.
+.||....||.
+.||....||.
+.|L-7F-J|.
+.|..||..|.
+.L--JL--J.
+..........",
+            4,
+        ),
+        (
+            ".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...",
+            8,
+        ),
+        (
+            "FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L",
+            10,
+        ),
+    ];
+
+    #[test]
+    fn shoelace_and_raycast_agree() {
+        for (text, enclosed) in EXAMPLES {
+            let maze = maze(text);
+            assert_eq!(enclosed_by_shoelace(&maze.loop_path), enclosed);
+            assert_eq!(enclosed_by_raycast(&maze), enclosed);
+        }
+    }
+}
@@ -8,6 +8,11 @@ fn main() {
     let args: Vec<String> = env::args().collect();
 
     let file = &args[1];
+    if args.get(2).is_some_and(|a| a == "--explore") {
+        explore(file);
+        return;
+    }
+
     let start1 = Instant::now();
     let p1 = part1(file);
     let duration1 = start1.elapsed();
@@ -19,65 +24,209 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-fn parse_grid(file: &str, expand_count: u32) -> Vec<(u32, u32)> {
-    let mut expand_cols: Vec<bool> = vec![];
-    let mut galaxies: Vec<(u32, u32)> = vec![];
-    let mut row_index: u32 = 0;
-    for l in read_lines(file).unwrap() {
-        let line = l.unwrap();
-        if expand_cols.is_empty() {
-            for _ in 0..line.len() {
-                expand_cols.push(true);
+// the galaxies where they are in the input, plus prefix sums of the empty
+// rows and columns so expanding a coordinate is a lookup
+struct Universe {
+    galaxies: Vec<(usize, usize)>,
+    // empty_rows_before[r] is how many empty rows there are above row r
+    empty_rows_before: Vec<u64>,
+    empty_cols_before: Vec<u64>,
+}
+
+impl Universe {
+    fn parse(file: &str) -> Universe {
+        let mut galaxies: Vec<(usize, usize)> = vec![];
+        let mut row_has_galaxy: Vec<bool> = vec![];
+        let mut col_has_galaxy: Vec<bool> = vec![];
+        for (row, l) in read_lines(file).unwrap().enumerate() {
+            let line = l.unwrap();
+            if col_has_galaxy.len() < line.len() {
+                col_has_galaxy.resize(line.len(), false);
             }
-        }
-        let row_galaxies: Vec<usize> = line.match_indices('#').map(|m| m.0).collect();
-        if row_galaxies.is_empty() {
-            row_index += expand_count;
-        } else {
-            for galaxy_col in row_galaxies {
-                expand_cols[galaxy_col] = false;
-                galaxies.push((row_index, galaxy_col as u32));
+            row_has_galaxy.push(false);
+            for (col, _) in line.match_indices('#') {
+                row_has_galaxy[row] = true;
+                col_has_galaxy[col] = true;
+                galaxies.push((row, col));
             }
         }
-        row_index += 1;
+        Universe {
+            galaxies,
+            empty_rows_before: empty_before(&row_has_galaxy),
+            empty_cols_before: empty_before(&col_has_galaxy),
+        }
+    }
+
+    // galaxy positions once every empty row and column has been replaced by
+    // `factor` empty rows or columns
+    fn expanded(&self, factor: u64) -> Vec<(u64, u64)> {
+        let extra = factor - 1;
+        self.galaxies
+            .iter()
+            .map(|&(row, col)| {
+                (
+                    row as u64 + extra * self.empty_rows_before[row],
+                    col as u64 + extra * self.empty_cols_before[col],
+                )
+            })
+            .collect()
+    }
+}
+
+fn empty_before(has_galaxy: &[bool]) -> Vec<u64> {
+    let mut before = Vec::with_capacity(has_galaxy.len() + 1);
+    let mut count = 0;
+    before.push(count);
+    for &has in has_galaxy {
+        if !has {
+            count += 1;
+        }
+        before.push(count);
+    }
+    before
+}
+
+// manhattan distance splits into rows and columns, and along one axis the
+// sum of all pairwise distances over sorted coordinates is
+// sum of (x_i * i - (x_0 + ... + x_{i-1})), so no need to look at every pair
+fn sum_of_distances(galaxies: &[(u64, u64)]) -> u128 {
+    let mut rows: Vec<u64> = galaxies.iter().map(|g| g.0).collect();
+    let mut cols: Vec<u64> = galaxies.iter().map(|g| g.1).collect();
+    axis_sum_of_distances(&mut rows) + axis_sum_of_distances(&mut cols)
+}
+
+fn axis_sum_of_distances(coords: &mut [u64]) -> u128 {
+    coords.sort_unstable();
+    let mut sum: u128 = 0;
+    let mut prefix: u128 = 0;
+    for (i, &x) in coords.iter().enumerate() {
+        sum += x as u128 * i as u128 - prefix;
+        prefix += x as u128;
     }
-    let expand_col_i: Vec<usize> = expand_cols
+    sum
+}
+
+// for every galaxy, the sum of its distances to all the others. same trick
+// as the all-pairs sum: along one sorted axis everything before x_i is
+// x_i * i - prefix away and everything after is suffix - x_i * (n - 1 - i)
+fn per_galaxy_distance_sums(galaxies: &[(u64, u64)]) -> Vec<u128> {
+    let mut sums = vec![0; galaxies.len()];
+    for axis in [|g: &(u64, u64)| g.0, |g: &(u64, u64)| g.1] {
+        let mut order: Vec<usize> = (0..galaxies.len()).collect();
+        order.sort_unstable_by_key(|&i| axis(&galaxies[i]));
+        let total: u128 = galaxies.iter().map(|g| axis(g) as u128).sum();
+        let mut prefix: u128 = 0;
+        for (rank, &i) in order.iter().enumerate() {
+            let x = axis(&galaxies[i]) as u128;
+            let after = (galaxies.len() - 1 - rank) as u128;
+            sums[i] += x * rank as u128 - prefix + (total - prefix - x) - x * after;
+            prefix += x;
+        }
+    }
+    sums
+}
+
+fn distance(a: (u64, u64), b: (u64, u64)) -> u64 {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+// the k galaxies closest to galaxy `from` as (galaxy, distance), nearest
+// first, with ties going to the lower galaxy number
+fn nearest_neighbours(galaxies: &[(u64, u64)], from: usize, k: usize) -> Vec<(usize, u64)> {
+    let mut others: Vec<(usize, u64)> = (0..galaxies.len())
+        .filter(|&i| i != from)
+        .map(|i| (i, distance(galaxies[from], galaxies[i])))
+        .collect();
+    others.sort_unstable_by_key(|&(i, d)| (d, i));
+    others.truncate(k);
+    others
+}
+
+// the two galaxies furthest apart. rotating 45 degrees turns manhattan
+// distance into max(|du|, |dv|) with u = r + c and v = r - c, so the
+// furthest pair is whichever of u or v has the bigger spread
+fn farthest_pair(galaxies: &[(u64, u64)]) -> Option<(usize, usize, u64)> {
+    if galaxies.len() < 2 {
+        return None;
+    }
+    let rotated: Vec<(i128, i128)> = galaxies
         .iter()
-        .enumerate()
-        .filter(|&(_, expand)| *expand)
-        .map(|(i, _)| i)
+        .map(|g| ((g.0 + g.1) as i128, g.0 as i128 - g.1 as i128))
         .collect();
-    for i in 0..galaxies.len() {
-        let (gr, gc) = galaxies[i];
-        let new_gc =
-            gc + (expand_count * expand_col_i.iter().filter(|&i| *i < gc as usize).count() as u32);
-        galaxies[i] = (gr, new_gc);
+    let mut best: Option<(usize, usize, u64)> = None;
+    for key in [|r: &(i128, i128)| r.0, |r: &(i128, i128)| r.1] {
+        let lo = (0..galaxies.len())
+            .min_by_key(|&i| key(&rotated[i]))
+            .unwrap();
+        let hi = (0..galaxies.len())
+            .max_by_key(|&i| key(&rotated[i]))
+            .unwrap();
+        let d = distance(galaxies[lo], galaxies[hi]);
+        if best.is_none_or(|(_, _, best_d)| d > best_d) {
+            best = Some((lo.min(hi), lo.max(hi), d));
+        }
     }
-    galaxies
+    best
 }
 
-fn manhattan_distance(galaxies: Vec<(u32, u32)>) -> u64 {
-    let mut sum = 0;
-    for i in 0..galaxies.len() - 1 {
-        let gal1 = galaxies[i];
-        for j in i..galaxies.len() {
-            let gal2 = galaxies[j];
-            // that's manhattan distance baby
-            sum += (gal1.0 as i32 - gal2.0 as i32).abs() as u64
-                + (gal1.1 as i32 - gal2.1 as i32).abs() as u64;
+// reads commands from stdin so the same parsed universe can be poked at
+// with different expansion factors
+fn explore(file: &str) {
+    let universe = Universe::parse(file);
+    let mut factor = 2;
+    let mut galaxies = universe.expanded(factor);
+    println!(
+        "{} galaxies, expansion factor {}. commands: factor N, sum, totals, nearest G K, farthest, quit",
+        galaxies.len(),
+        factor
+    );
+    for l in io::stdin().lock().lines() {
+        let line = l.unwrap();
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["factor", n] => match n.parse::<u64>() {
+                Ok(n) if n > 0 => {
+                    factor = n;
+                    galaxies = universe.expanded(factor);
+                    println!("expansion factor {}", factor);
+                }
+                _ => println!("factor needs to be a positive number"),
+            },
+            ["sum"] => println!("{}", sum_of_distances(&galaxies)),
+            ["totals"] => {
+                for (i, total) in per_galaxy_distance_sums(&galaxies).iter().enumerate() {
+                    println!("{} {:?}: {}", i, galaxies[i], total);
+                }
+            }
+            ["nearest", g, k] => match (g.parse::<usize>(), k.parse::<usize>()) {
+                (Ok(g), Ok(k)) if g < galaxies.len() => {
+                    for (i, d) in nearest_neighbours(&galaxies, g, k) {
+                        println!("{} {:?}: {}", i, galaxies[i], d);
+                    }
+                }
+                _ => println!("usage: nearest <galaxy> <k>"),
+            },
+            ["farthest"] => match farthest_pair(&galaxies) {
+                Some((a, b, d)) => {
+                    println!("{} {:?} and {} {:?}: {}", a, galaxies[a], b, galaxies[b], d)
+                }
+                None => println!("need at least two galaxies"),
+            },
+            ["quit"] => break,
+            [] => {}
+            _ => println!("unknown command {}", line),
         }
     }
-    sum
 }
 
-fn part1(file: &str) -> u64 {
-    let galaxies = parse_grid(file, 1);
-    manhattan_distance(galaxies)
+fn part1(file: &str) -> u128 {
+    let universe = Universe::parse(file);
+    sum_of_distances(&universe.expanded(2))
 }
 
-fn part2(file: &str) -> u64 {
-    let galaxies = parse_grid(file, 999999);
-    manhattan_distance(galaxies)
+fn part2(file: &str) -> u128 {
+    let universe = Universe::parse(file);
+    sum_of_distances(&universe.expanded(1_000_000))
 }
 
 // borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
@@ -1,14 +1,28 @@
-use std::collections::HashMap;
+mod nonogram;
+
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
 use std::time::Instant;
 
+use nonogram::{Puzzle, Solution};
+use rand::Rng;
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let file = &args[1];
+    if args.get(2).is_some_and(|a| a == "--show") {
+        let limit = args.get(3).map_or(5, |n| n.parse::<usize>().unwrap());
+        show_arrangements(file, 1, limit);
+        return;
+    }
+    if args.get(2).is_some_and(|a| a == "--nonogram") {
+        solve_nonogram(file);
+        return;
+    }
+
     let start1 = Instant::now();
     let p1 = part1(file);
     let duration1 = start1.elapsed();
@@ -20,90 +34,199 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-fn count_possible_solutions(
-    chars: &Vec<char>,
-    counts: &Vec<usize>,
-    memo: &mut HashMap<(String, Vec<usize>), u64>,
-) -> u64 {
-    let key: (String, Vec<usize>) = (chars.iter().collect(), counts.clone());
-    if memo.contains_key(&key) {
-        return *memo.get(&key).unwrap();
+// one row of springs and its damaged group sizes
+struct Row {
+    springs: Vec<char>,
+    groups: Vec<usize>,
+}
+
+impl Row {
+    // parses a line, repeating it `unfold` times (joined by ? for the
+    // springs) like part 2 wants
+    fn parse(line: &str, unfold: usize) -> Row {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let groups: Vec<usize> = parts[1]
+            .split(',')
+            .map(|s| s.parse::<usize>().unwrap())
+            .collect();
+        Row {
+            springs: vec![parts[0]; unfold].join("?").chars().collect(),
+            groups: groups.repeat(unfold),
+        }
+    }
+}
+
+// ways[i][g] is how many ways springs[i..] can be filled in to match
+// groups[g..], built from the back so each entry only looks further along
+struct Arrangements {
+    springs: Vec<char>,
+    groups: Vec<usize>,
+    // how many springs before index i are definitely operational, so
+    // checking a group fits is a subtraction
+    dots_before: Vec<usize>,
+    ways: Vec<u128>,
+}
+
+impl Arrangements {
+    fn new(row: Row) -> Arrangements {
+        let Row { springs, groups } = row;
+        let n = springs.len();
+        let mut dots_before = Vec::with_capacity(n + 1);
+        dots_before.push(0);
+        for &c in &springs {
+            dots_before.push(dots_before[dots_before.len() - 1] + (c == '.') as usize);
+        }
+
+        let mut arrangements = Arrangements {
+            ways: vec![0; (n + 1) * (groups.len() + 1)],
+            springs,
+            groups,
+            dots_before,
+        };
+        let m = arrangements.groups.len();
+        arrangements.ways[n * (m + 1) + m] = 1;
+        for i in (0..n).rev() {
+            for g in 0..=m {
+                let mut ways = 0;
+                if arrangements.springs[i] != '#' {
+                    ways += arrangements.ways_from(i + 1, g);
+                }
+                if arrangements.fits_group(i, g) {
+                    ways += arrangements.ways_from(arrangements.after_group(i, g), g + 1);
+                }
+                arrangements.ways[i * (m + 1) + g] = ways;
+            }
+        }
+        arrangements
+    }
+
+    fn ways_from(&self, i: usize, g: usize) -> u128 {
+        self.ways[i * (self.groups.len() + 1) + g]
     }
 
-    let count;
-    if counts.is_empty() && !chars.iter().any(|&c| c == '#') {
-        count = 1;
-    } else if counts.is_empty() && chars.iter().any(|&c| c == '#') {
-        count = 0;
-    } else if chars.is_empty() && !counts.is_empty() {
-        count = 0;
-    } else {
-        if chars[0] == '.' {
-            count = count_possible_solutions(&chars[1..].to_vec(), counts, memo);
-        } else if chars[0] == '#' {
-            let target_count = counts[0];
-            if chars.len() >= target_count
-                && chars[0..target_count].iter().all(|&c| c == '#' || c == '?')
-                && (chars.len() == target_count || chars[target_count] != '#')
-            {
-                let next_chars = if chars.len() == target_count {
-                    vec![]
-                } else {
-                    chars[target_count + 1..].to_vec()
-                };
-                count = count_possible_solutions(&next_chars, &counts[1..].to_vec(), memo)
+    // whether group g can start at spring i: nothing operational inside it
+    // and nothing damaged right after it
+    fn fits_group(&self, i: usize, g: usize) -> bool {
+        let Some(&len) = self.groups.get(g) else {
+            return false;
+        };
+        let end = i + len;
+        end <= self.springs.len()
+            && self.dots_before[end] == self.dots_before[i]
+            && self.springs.get(end) != Some(&'#')
+    }
+
+    // where to carry on from after placing group g at spring i, skipping
+    // the spring that has to separate it from the next group
+    fn after_group(&self, i: usize, g: usize) -> usize {
+        (i + self.groups[g] + 1).min(self.springs.len())
+    }
+
+    fn count(&self) -> u128 {
+        self.ways_from(0, 0)
+    }
+
+    // the k-th arrangement (from 0) in lexicographic order, where # sorts
+    // before . like it does in ascii. at each spring every arrangement that
+    // starts a group here comes before every one that doesn't, so the table
+    // says which way to go without trying anything
+    fn kth(&self, mut k: u128) -> Option<String> {
+        if k >= self.count() {
+            return None;
+        }
+        let mut arrangement = String::with_capacity(self.springs.len());
+        let (mut i, mut g) = (0, 0);
+        while i < self.springs.len() {
+            let starting_group = if self.fits_group(i, g) {
+                self.ways_from(self.after_group(i, g), g + 1)
             } else {
-                count = 0;
+                0
+            };
+            if k < starting_group {
+                let next = self.after_group(i, g);
+                arrangement.extend(std::iter::repeat_n('#', self.groups[g]));
+                if next > i + self.groups[g] {
+                    arrangement.push('.');
+                }
+                i = next;
+                g += 1;
+            } else {
+                k -= starting_group;
+                arrangement.push('.');
+                i += 1;
             }
-        } else {
-            // chars[0] == '?'
-            let mut v1 = chars.clone();
-            v1[0] = '#';
-            let mut v2 = chars.clone();
-            v2[0] = '.';
-            count = count_possible_solutions(&v1, counts, memo)
-                + count_possible_solutions(&v2, counts, memo);
+        }
+        Some(arrangement)
+    }
+
+    // every arrangement in lexicographic order, worked out one at a time
+    fn iter(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.count()).map(|k| self.kth(k).unwrap())
+    }
+
+    // an arrangement picked uniformly at random, or None if there aren't
+    // any. picking a random k and walking the table is the same as choosing
+    // each step with probability proportional to the ways it leaves
+    fn sample<R: Rng>(&self, rng: &mut R) -> Option<String> {
+        match self.count() {
+            0 => None,
+            n => self.kth(rng.gen_range(0..n)),
         }
     }
-    memo.insert(key, count);
-    count
 }
 
-fn part1(file: &str) -> u64 {
-    let mut sum: u64 = 0;
+fn total_arrangements(file: &str, unfold: usize) -> u128 {
+    let mut sum: u128 = 0;
     for l in read_lines(file).unwrap() {
         let line = l.unwrap();
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        let springs = parts[0];
-        let counts: Vec<usize> = parts[1]
-            .split(',')
-            .map(|s| s.parse::<usize>().unwrap())
-            .collect();
-
-        let mut memo: HashMap<(String, Vec<usize>), u64> = HashMap::new();
-
-        sum += count_possible_solutions(&springs.chars().collect(), &counts, &mut memo);
+        sum += Arrangements::new(Row::parse(&line, unfold)).count();
     }
     sum
 }
 
-fn part2(file: &str) -> u64 {
-    let mut sum: u64 = 0;
+fn part1(file: &str) -> u128 {
+    total_arrangements(file, 1)
+}
+
+fn part2(file: &str) -> u128 {
+    total_arrangements(file, 5)
+}
+
+// prints some actual arrangements for each row, for when a count looks off
+fn show_arrangements(file: &str, unfold: usize, limit: usize) {
+    let mut rng = rand::thread_rng();
     for l in read_lines(file).unwrap() {
         let line = l.unwrap();
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        let springs = vec![parts[0]; 5].join("?");
-        let counts: Vec<usize> = vec![parts[1]; 5]
-            .join(",")
-            .split(',')
-            .map(|s| s.parse::<usize>().unwrap())
-            .collect();
-
-        let mut memo: HashMap<(String, Vec<usize>), u64> = HashMap::new();
+        let arrangements = Arrangements::new(Row::parse(&line, unfold));
+        println!("{}: {} arrangements", line, arrangements.count());
+        for arrangement in arrangements.iter().take(limit) {
+            println!("  {}", arrangement);
+        }
+        if let Some(arrangement) = arrangements.sample(&mut rng) {
+            println!("  random: {}", arrangement);
+        }
+    }
+}
 
-        sum += count_possible_solutions(&springs.chars().collect(), &counts, &mut memo) as u64;
+fn solve_nonogram(file: &str) {
+    let print_grid = |grid: &[Vec<char>]| {
+        for row in grid {
+            println!("{}", row.iter().collect::<String>());
+        }
+    };
+    match Puzzle::parse(file).solve() {
+        Solution::Unique(grid) => {
+            println!("unique solution:");
+            print_grid(&grid);
+        }
+        Solution::Multiple(first, second) => {
+            println!("multiple solutions, including:");
+            print_grid(&first);
+            println!("and:");
+            print_grid(&second);
+        }
+        Solution::Impossible => println!("no solution"),
     }
-    sum
 }
 
 // borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
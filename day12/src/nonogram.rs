@@ -0,0 +1,154 @@
+// a full nonogram is just a grid where every row and every column is one of
+// the spring rows from part 1, with # for filled and . for empty. so the
+// arrangement counter doubles as a line solver: a cell is forced if only
+// one of # or . leaves any arrangements for its line. when that stops
+// getting anywhere we guess a cell and recurse.
+
+use crate::{read_lines, Arrangements, Row};
+
+pub struct Puzzle {
+    rows: Vec<Vec<usize>>,
+    cols: Vec<Vec<usize>>,
+}
+
+pub enum Solution {
+    Unique(Vec<Vec<char>>),
+    // two different grids that both fit the clues
+    Multiple(Vec<Vec<char>>, Vec<Vec<char>>),
+    Impossible,
+}
+
+impl Puzzle {
+    // row clues one per line, a blank line, then column clues one per line.
+    // a line with no filled cells has the clue 0
+    pub fn parse(file: &str) -> Puzzle {
+        let mut rows: Vec<Vec<usize>> = Vec::new();
+        let mut cols: Vec<Vec<usize>> = Vec::new();
+        let mut in_cols = false;
+        for l in read_lines(file).unwrap() {
+            let line = l.unwrap();
+            if line.trim().is_empty() {
+                in_cols = true;
+                continue;
+            }
+            let clue: Vec<usize> = line
+                .trim()
+                .split(',')
+                .map(|s| s.trim().parse::<usize>().unwrap())
+                .filter(|&n| n > 0)
+                .collect();
+            if in_cols {
+                cols.push(clue);
+            } else {
+                rows.push(clue);
+            }
+        }
+        Puzzle { rows, cols }
+    }
+
+    pub fn solve(&self) -> Solution {
+        let grid = vec![vec!['?'; self.cols.len()]; self.rows.len()];
+        let mut found: Vec<Vec<Vec<char>>> = Vec::new();
+        self.search(grid, &mut found);
+        match found.len() {
+            0 => Solution::Impossible,
+            1 => Solution::Unique(found.remove(0)),
+            _ => {
+                let second = found.remove(1);
+                Solution::Multiple(found.remove(0), second)
+            }
+        }
+    }
+
+    // depth first search that gives up once it's found two solutions, since
+    // that's enough to know the puzzle isn't unique
+    fn search(&self, mut grid: Vec<Vec<char>>, found: &mut Vec<Vec<Vec<char>>>) {
+        if found.len() >= 2 || !self.propagate(&mut grid) {
+            return;
+        }
+        let unknown = grid
+            .iter()
+            .enumerate()
+            .find_map(|(r, row)| row.iter().position(|&c| c == '?').map(|c| (r, c)));
+        let Some((r, c)) = unknown else {
+            found.push(grid);
+            return;
+        };
+        for guess in ['#', '.'] {
+            let mut next = grid.clone();
+            next[r][c] = guess;
+            self.search(next, found);
+        }
+    }
+
+    // line solves every row and column until nothing changes. returns false
+    // if some line can't be filled in at all
+    fn propagate(&self, grid: &mut [Vec<char>]) -> bool {
+        let mut dirty_rows = vec![true; self.rows.len()];
+        let mut dirty_cols = vec![true; self.cols.len()];
+        while dirty_rows.contains(&true) || dirty_cols.contains(&true) {
+            for r in 0..self.rows.len() {
+                if !std::mem::take(&mut dirty_rows[r]) {
+                    continue;
+                }
+                let Some(solved) = solve_line(&grid[r], &self.rows[r]) else {
+                    return false;
+                };
+                for (c, &cell) in solved.iter().enumerate() {
+                    if grid[r][c] != cell {
+                        grid[r][c] = cell;
+                        dirty_cols[c] = true;
+                    }
+                }
+            }
+            for c in 0..self.cols.len() {
+                if !std::mem::take(&mut dirty_cols[c]) {
+                    continue;
+                }
+                let col: Vec<char> = grid.iter().map(|row| row[c]).collect();
+                let Some(solved) = solve_line(&col, &self.cols[c]) else {
+                    return false;
+                };
+                for (r, &cell) in solved.iter().enumerate() {
+                    if grid[r][c] != cell {
+                        grid[r][c] = cell;
+                        dirty_rows[r] = true;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+// fills in every cell of the line that's the same in all of its
+// arrangements, or None if there aren't any arrangements
+fn solve_line(line: &[char], clue: &[usize]) -> Option<Vec<char>> {
+    let count = |cells: &[char]| {
+        Arrangements::new(Row {
+            springs: cells.to_vec(),
+            groups: clue.to_vec(),
+        })
+        .count()
+    };
+    if count(line) == 0 {
+        return None;
+    }
+    let mut solved = line.to_vec();
+    for i in 0..line.len() {
+        if line[i] != '?' {
+            continue;
+        }
+        let mut trial = line.to_vec();
+        trial[i] = '#';
+        let can_fill = count(&trial) > 0;
+        trial[i] = '.';
+        let can_empty = count(&trial) > 0;
+        if !can_empty {
+            solved[i] = '#';
+        } else if !can_fill {
+            solved[i] = '.';
+        }
+    }
+    Some(solved)
+}
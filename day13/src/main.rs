@@ -1,4 +1,3 @@
-use std::collections::HashSet;
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
@@ -9,6 +8,11 @@ fn main() {
     let args: Vec<String> = env::args().collect();
 
     let file = &args[1];
+    if args.get(2).is_some_and(|a| a == "--symmetries") {
+        show_symmetries(file);
+        return;
+    }
+
     let start1 = Instant::now();
     let p1 = part1(file);
     let duration1 = start1.elapsed();
@@ -20,132 +24,178 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-fn parse(file: &str) -> Vec<Vec<Vec<char>>> {
-    let mut all_grids: Vec<Vec<Vec<char>>> = Vec::new();
-    let mut current_grid: Vec<Vec<char>> = Vec::new();
-    for l in read_lines(file).unwrap() {
-        let line = l.unwrap();
-        if line == "" {
-            all_grids.push(current_grid);
-            current_grid = Vec::new();
-        } else {
-            current_grid.push(line.chars().collect());
-        }
-    }
-    all_grids.push(current_grid);
-    all_grids
+// a pattern with every row and column packed into a bitmask (bit i set if
+// cell i is a #), so comparing two lines is an xor and a popcount
+struct Pattern {
+    rows: Vec<u64>,
+    cols: Vec<u64>,
 }
 
-fn reflect_indices(vec: &Vec<char>) -> Vec<usize> {
-    let mut indices: Vec<usize> = vec![];
-    // line is between elements i and i + 1
-    for reflect_index in 0..vec.len() - 1 {
-        let first_chunk_len = reflect_index + 1;
-        let last_chunk_len = vec.len() - reflect_index - 1;
-        let first_chunk: Vec<char>;
-        let mut last_chunk: Vec<char>;
-        if first_chunk_len > last_chunk_len {
-            first_chunk = vec[reflect_index - last_chunk_len + 1..reflect_index + 1].to_vec();
-            last_chunk = vec[reflect_index + 1..vec.len()].to_vec();
-        } else if first_chunk_len < last_chunk_len {
-            first_chunk = vec[0..reflect_index + 1].to_vec();
-            last_chunk = vec[reflect_index + 1..reflect_index + 1 + first_chunk_len].to_vec();
-        } else {
-            first_chunk = vec[0..reflect_index + 1].to_vec();
-            last_chunk = vec[reflect_index + 1..vec.len()].to_vec();
+impl Pattern {
+    fn new(grid: &[Vec<char>]) -> Pattern {
+        assert!(
+            grid.len() <= 64 && grid[0].len() <= 64,
+            "patterns can't be bigger than 64x64"
+        );
+        let mut rows = vec![0; grid.len()];
+        let mut cols = vec![0; grid[0].len()];
+        for (r, line) in grid.iter().enumerate() {
+            for (c, &ch) in line.iter().enumerate() {
+                if ch == '#' {
+                    rows[r] |= 1 << c;
+                    cols[c] |= 1 << r;
+                }
+            }
         }
-        last_chunk.reverse();
-        if first_chunk == last_chunk {
-            indices.push(reflect_index);
+        Pattern { rows, cols }
+    }
+
+    // every line of reflection along with how many cells don't match across
+    // it, vertical lines first then horizontal, each in order
+    fn axes(&self) -> Vec<(Axis, u32)> {
+        let vertical =
+            (0..self.cols.len() - 1).map(|c| (Axis::Vertical(c), mismatches(&self.cols, c)));
+        let horizontal =
+            (0..self.rows.len() - 1).map(|r| (Axis::Horizontal(r), mismatches(&self.rows, r)));
+        vertical.chain(horizontal).collect()
+    }
+
+    // every line of reflection that's off by exactly `smudges` cells
+    fn reflections_with_smudges(&self, smudges: u32) -> Vec<Axis> {
+        self.axes()
+            .into_iter()
+            .filter(|&(_, m)| m == smudges)
+            .map(|(axis, _)| axis)
+            .collect()
+    }
+
+    // every kind of symmetry we know about with how many cells would need
+    // fixing to make it exact (so a single smudge counts once even though it
+    // spoils two cells), closest first
+    fn symmetries(&self) -> Vec<(Symmetry, u32)> {
+        let mut symmetries: Vec<(Symmetry, u32)> = self
+            .axes()
+            .into_iter()
+            .map(|(axis, m)| (Symmetry::Axis(axis), m))
+            .collect();
+
+        let height = self.rows.len();
+        let width = self.cols.len();
+        if height == width {
+            // (r, c) swaps with (c, r), so row r should match column r
+            let transpose: u32 = (0..height)
+                .map(|i| (self.rows[i] ^ self.cols[i]).count_ones())
+                .sum();
+            // (r, c) swaps with (n - 1 - c, n - 1 - r)
+            let anti_transpose: u32 = (0..height)
+                .map(|i| (self.rows[i] ^ reverse(self.cols[height - 1 - i], width)).count_ones())
+                .sum();
+            symmetries.push((Symmetry::Transpose, transpose / 2));
+            symmetries.push((Symmetry::AntiTranspose, anti_transpose / 2));
         }
+        // (r, c) swaps with (h - 1 - r, w - 1 - c)
+        let rotation: u32 = (0..height)
+            .map(|i| (self.rows[i] ^ reverse(self.rows[height - 1 - i], width)).count_ones())
+            .sum();
+        symmetries.push((Symmetry::Rotation180, rotation / 2));
+
+        symmetries.sort_by_key(|&(symmetry, m)| (m, symmetry));
+        symmetries
     }
-    indices
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
-struct Reflection {
-    row: Option<usize>,
-    col: Option<usize>,
+// flips the lowest `width` bits of a line end to end
+fn reverse(line: u64, width: usize) -> u64 {
+    line.reverse_bits() >> (64 - width)
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+enum Symmetry {
+    Axis(Axis),
+    // mirrored across the top left to bottom right diagonal
+    Transpose,
+    // mirrored across the top right to bottom left diagonal
+    AntiTranspose,
+    // the same after turning it upside down
+    Rotation180,
+}
+
+// a line of reflection, sitting just after the given column or row
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+enum Axis {
+    Vertical(usize),
+    Horizontal(usize),
 }
 
-fn find_reflections(grid: &Vec<Vec<char>>) -> Vec<Reflection> {
-    let mut reflections = Vec::new();
-    let mut common_rows: HashSet<usize> = HashSet::from_iter(0..grid[0].len() - 1);
-    for r in grid {
-        let reflect_rows = HashSet::from_iter(reflect_indices(&r));
-        common_rows = HashSet::from_iter(common_rows.intersection(&reflect_rows).map(|&r| r));
-        if common_rows.is_empty() {
-            break;
+impl Axis {
+    fn score(&self) -> u32 {
+        match self {
+            Axis::Vertical(c) => *c as u32 + 1,
+            Axis::Horizontal(r) => (*r as u32 + 1) * 100,
         }
     }
-    for r in common_rows {
-        reflections.push(Reflection {
-            row: Some(r),
-            col: None,
-        })
-    }
+}
 
-    // ok let's do columns then
-    let mut common_cols: HashSet<usize> = HashSet::from_iter(0..grid.len() - 1);
-    for c in 0..grid[0].len() {
-        let column: Vec<char> = grid.iter().map(|r| r[c]).collect();
-        let reflect_cols: HashSet<usize> = HashSet::from_iter(reflect_indices(&column));
-        common_cols = HashSet::from_iter(common_cols.intersection(&reflect_cols).map(|&c| c));
-    }
-    for c in common_cols {
-        reflections.push(Reflection {
-            row: None,
-            col: Some(c),
-        })
-    }
-    reflections
+// how many cells differ when folding `lines` between line `after` and
+// line `after + 1`
+fn mismatches(lines: &[u64], after: usize) -> u32 {
+    lines[..=after]
+        .iter()
+        .rev()
+        .zip(&lines[after + 1..])
+        .map(|(a, b)| (a ^ b).count_ones())
+        .sum()
 }
 
-fn part1(file: &str) -> u32 {
-    let grids = parse(file);
-    let mut sum: u32 = 0;
-    for grid in grids {
-        let reflections = find_reflections(&grid);
-        let reflection = reflections.iter().next().unwrap();
-        if reflection.row.is_some() {
-            sum += reflection.row.unwrap() as u32 + 1;
+fn parse(file: &str) -> Vec<Pattern> {
+    let mut all_patterns: Vec<Pattern> = Vec::new();
+    let mut current_grid: Vec<Vec<char>> = Vec::new();
+    for l in read_lines(file).unwrap() {
+        let line = l.unwrap();
+        if line.is_empty() {
+            all_patterns.push(Pattern::new(&current_grid));
+            current_grid = Vec::new();
         } else {
-            sum += (reflection.col.unwrap() as u32 + 1) * 100;
+            current_grid.push(line.chars().collect());
         }
     }
-    sum
+    all_patterns.push(Pattern::new(&current_grid));
+    all_patterns
 }
 
-fn part2(file: &str) -> u32 {
-    let grids = parse(file);
+fn summarize(file: &str, smudges: u32) -> u32 {
     let mut sum = 0;
-    'grid: for grid in grids {
-        let original_reflection = *find_reflections(&grid).iter().next().unwrap();
-        for smudge_r in 0..grid.len() {
-            for smudge_c in 0..grid[0].len() {
-                let mut new_grid = grid.clone();
-                if new_grid[smudge_r][smudge_c] == '.' {
-                    new_grid[smudge_r][smudge_c] = '#';
-                } else {
-                    new_grid[smudge_r][smudge_c] = '.';
-                }
-                let smudge_reflections = find_reflections(&new_grid);
-                for sr in smudge_reflections {
-                    if sr != original_reflection {
-                        if sr.row.is_some() {
-                            sum += sr.row.unwrap() as u32 + 1;
-                        } else {
-                            sum += (sr.col.unwrap() as u32 + 1) * 100;
-                        }
-                        continue 'grid;
-                    }
-                }
-            }
+    for (i, pattern) in parse(file).iter().enumerate() {
+        match pattern.reflections_with_smudges(smudges)[..] {
+            [axis] => sum += axis.score(),
+            [] => panic!("pattern {} has no line of reflection", i),
+            ref axes => panic!(
+                "pattern {} has more than one line of reflection: {:?}",
+                i, axes
+            ),
         }
     }
     sum
 }
 
+// prints every symmetry of every pattern, so ambiguous ones stand out
+fn show_symmetries(file: &str) {
+    for (i, pattern) in parse(file).iter().enumerate() {
+        println!("pattern {}:", i);
+        for (symmetry, m) in pattern.symmetries() {
+            println!("  {:?}: {} mismatched", symmetry, m);
+        }
+    }
+}
+
+fn part1(file: &str) -> u32 {
+    summarize(file, 0)
+}
+
+fn part2(file: &str) -> u32 {
+    summarize(file, 1)
+}
+
 // borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
 fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
 where
@@ -1,14 +1,23 @@
-use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::rc::Rc;
 use std::time::Instant;
 
+use cycle::nth_state;
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let file = &args[1];
+    if args.get(2).is_some_and(|a| a == "--program") {
+        // day14 <file> --program <tilts, like NWSE> [times]
+        let times = args.get(4).map_or(1, |t| t.parse::<u64>().unwrap());
+        explore(file, &args[3], times);
+        return;
+    }
+
     let start1 = Instant::now();
     let p1 = part1(file);
     let duration1 = start1.elapsed();
@@ -20,125 +29,253 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-struct Grid {
-    grid: Vec<Vec<char>>,
-    rocks: Vec<(usize, usize)>,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Direction {
+    North,
+    South,
+    East,
+    West,
 }
 
-#[derive(PartialEq, Eq)]
-struct Direction {
-    dr: i32,
-    dc: i32,
+// the platform as bitsets: bit c of row r (and bit r of column c) is set if
+// there's a round rock at (r, c). both views are kept so tilting along
+// either axis works on whole lines at once
+#[derive(Clone)]
+struct Platform {
+    height: usize,
+    width: usize,
+    rock_rows: Vec<u128>,
+    rock_cols: Vec<u128>,
+    // the runs of cells between cube rocks in each row and column, as masks.
+    // tilting just piles all the rocks in a run up at one end of it. these
+    // never change, so clones of the platform share them
+    row_segments: Rc<Vec<Vec<u128>>>,
+    col_segments: Rc<Vec<Vec<u128>>>,
+    wall_rows: Rc<Vec<u128>>,
 }
 
-const NORTH: Direction = Direction { dr: -1, dc: 0 };
-const SOUTH: Direction = Direction { dr: 1, dc: 0 };
-const EAST: Direction = Direction { dr: 0, dc: 1 };
-const WEST: Direction = Direction { dr: 0, dc: -1 };
+impl Platform {
+    fn rows_to_cols(&self) -> Vec<u128> {
+        transpose(&self.rock_rows, self.width)
+    }
 
-fn parse(file: &str) -> Grid {
-    let mut grid: Vec<Vec<char>> = Vec::new();
-    let mut rocks: Vec<(usize, usize)> = Vec::new();
-    let mut r: usize = 0;
-    for l in read_lines(file).unwrap() {
-        let line = l.unwrap();
-        let mut row: Vec<char> = Vec::new();
-        for (i, c) in line.chars().enumerate() {
-            row.push(c);
-            if c == 'O' {
-                rocks.push((r, i));
+    fn cols_to_rows(&self) -> Vec<u128> {
+        transpose(&self.rock_cols, self.height)
+    }
+
+    fn tilt(&mut self, direction: Direction) {
+        match direction {
+            Direction::North | Direction::South => {
+                let toward_start = direction == Direction::North;
+                for (col, segments) in self.rock_cols.iter_mut().zip(self.col_segments.iter()) {
+                    *col = slide(*col, segments, toward_start);
+                }
+                self.rock_rows = self.cols_to_rows();
+            }
+            Direction::West | Direction::East => {
+                let toward_start = direction == Direction::West;
+                for (row, segments) in self.rock_rows.iter_mut().zip(self.row_segments.iter()) {
+                    *row = slide(*row, segments, toward_start);
+                }
+                self.rock_cols = self.rows_to_cols();
             }
         }
-        grid.push(row);
-        r += 1;
     }
-    Grid {
-        grid: grid,
-        rocks: rocks,
+
+    fn run(&mut self, program: &[Direction]) {
+        for &direction in program {
+            self.tilt(direction);
+        }
     }
-}
 
-fn roll(grid: &mut Grid, direction: &Direction) {
-    let mut blocked: Vec<bool> = vec![false; grid.rocks.len()];
-    loop {
-        let mut changes_made = false;
-        for i in 0..grid.rocks.len() {
-            let (rock_r, rock_c) = grid.rocks[i];
-            if blocked[i] {
-                continue;
-            }
-            // is rock at edge?
-            if (direction == &NORTH && rock_r == 0)
-                || (direction == &SOUTH && rock_r == grid.grid.len() - 1)
-                || (direction == &EAST && rock_c == grid.grid[0].len() - 1)
-                || (direction == &WEST && rock_c == 0)
-            {
-                blocked[i] = true;
-                continue;
-            }
-            let next_coords = (
-                (rock_r as i32 + direction.dr) as usize,
-                (rock_c as i32 + direction.dc) as usize,
-            );
-            let next_char = grid.grid[next_coords.0][next_coords.1];
-            if next_char == '#' {
-                blocked[i] = true;
-                continue;
-            }
+    // the total load on the given edge: each rock weighs as much as its
+    // distance from the opposite edge, counting its own row or column
+    fn load(&self, edge: Direction) -> u32 {
+        let (lines, len) = match edge {
+            Direction::North | Direction::South => (&self.rock_rows, self.height),
+            Direction::West | Direction::East => (&self.rock_cols, self.width),
+        };
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let weight = match edge {
+                    Direction::North | Direction::West => len - i,
+                    Direction::South | Direction::East => i + 1,
+                };
+                line.count_ones() * weight as u32
+            })
+            .sum()
+    }
 
-            if next_char == '.' {
-                grid.grid[rock_r][rock_c] = '.';
-                grid.grid[next_coords.0][next_coords.1] = 'O';
-                grid.rocks[i] = (next_coords.0, next_coords.1);
-                changes_made = true;
+    fn render(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for (rocks, walls) in self.rock_rows.iter().zip(self.wall_rows.iter()) {
+            for c in 0..self.width {
+                text.push(if rocks & (1 << c) != 0 {
+                    'O'
+                } else if walls & (1 << c) != 0 {
+                    '#'
+                } else {
+                    '.'
+                });
             }
+            text.push('\n');
         }
-        if !changes_made {
-            break;
-        }
+        text
     }
 }
 
-fn part1(file: &str) -> u32 {
-    let mut grid = parse(file);
-    roll(&mut grid, &NORTH);
+// runs a tilt program `times` times, skipping ahead if the rocks start
+// going round in circles before then
+fn run_repeated(platform: &Platform, program: &[Direction], times: u64) -> Platform {
+    let run = |p: &Platform| {
+        let mut next = p.clone();
+        next.run(program);
+        next
+    };
+    nth_state(platform, run, |p| p.rock_rows.clone(), times)
+}
 
-    grid.rocks
-        .iter()
-        .map(|(r, _)| (grid.grid.len() - r) as u32)
-        .sum()
+fn parse_program(program: &str) -> Vec<Direction> {
+    program
+        .chars()
+        .map(|c| match c.to_ascii_uppercase() {
+            'N' => Direction::North,
+            'S' => Direction::South,
+            'E' => Direction::East,
+            'W' => Direction::West,
+            _ => panic!("unknown direction {}", c),
+        })
+        .collect()
 }
 
-fn part2(file: &str) -> u32 {
-    let mut grid = parse(file);
-    let dir_cycle = [NORTH, WEST, SOUTH, EAST];
+// packs the rocks in each segment of a line up against the start (lowest
+// bit) or end of the segment
+fn slide(line: u128, segments: &[u128], toward_start: bool) -> u128 {
+    let mut slid = 0;
+    for &segment in segments {
+        let count = (line & segment).count_ones();
+        if count == 0 {
+            continue;
+        }
+        let shift = if toward_start {
+            segment.trailing_zeros()
+        } else {
+            128 - segment.leading_zeros() - count
+        };
+        slid |= ones(count) << shift;
+    }
+    slid
+}
 
-    let mut hash: HashMap<Vec<(usize, usize)>, u32> = HashMap::new();
-    let mut loads: Vec<u32> = Vec::new();
+fn ones(count: u32) -> u128 {
+    if count == 128 {
+        u128::MAX
+    } else {
+        (1 << count) - 1
+    }
+}
 
-    let mut i = 1;
-    let ret_load: u32;
-    loop {
-        for dir in dir_cycle.iter() {
-            roll(&mut grid, dir);
+// turns rows into columns or back again. only visits set bits, so it's as
+// cheap as the number of rocks
+fn transpose(lines: &[u128], other_len: usize) -> Vec<u128> {
+    let mut transposed = vec![0; other_len];
+    for (i, &line) in lines.iter().enumerate() {
+        let mut bits = line;
+        while bits != 0 {
+            let j = bits.trailing_zeros() as usize;
+            transposed[j] |= 1 << i;
+            bits &= bits - 1;
         }
-        let mut new_rocks = grid.rocks.clone();
-        new_rocks.sort();
-        if let Some(start_i) = hash.get(&new_rocks) {
-            let load = ((1000000000 - i) % (i - start_i)) + start_i - 1;
-            ret_load = loads[load as usize];
-            break;
+    }
+    transposed
+}
+
+// the masks covering each run of non-cube cells in a line
+fn segments(walls: u128, len: usize) -> Vec<u128> {
+    let mut segments = Vec::new();
+    let mut current: u128 = 0;
+    for i in 0..len {
+        if walls & (1 << i) != 0 {
+            if current != 0 {
+                segments.push(current);
+            }
+            current = 0;
         } else {
-            let load: u32 = new_rocks
-                .iter()
-                .map(|(r, _)| (grid.grid.len() - r) as u32)
-                .sum();
-            hash.insert(new_rocks, i);
-            loads.push(load);
+            current |= 1 << i;
+        }
+    }
+    if current != 0 {
+        segments.push(current);
+    }
+    segments
+}
+
+fn parse(file: &str) -> Platform {
+    let mut rock_rows: Vec<u128> = Vec::new();
+    let mut wall_rows: Vec<u128> = Vec::new();
+    let mut width = 0;
+    for l in read_lines(file).unwrap() {
+        let line = l.unwrap();
+        width = line.len();
+        assert!(width <= 128, "platform is too wide");
+        let mut rocks: u128 = 0;
+        let mut walls: u128 = 0;
+        for (c, ch) in line.chars().enumerate() {
+            match ch {
+                'O' => rocks |= 1 << c,
+                '#' => walls |= 1 << c,
+                _ => {}
+            }
         }
-        i += 1;
+        rock_rows.push(rocks);
+        wall_rows.push(walls);
+    }
+    let height = rock_rows.len();
+    assert!(height <= 128, "platform is too tall");
+    let wall_cols = transpose(&wall_rows, width);
+    Platform {
+        height,
+        width,
+        rock_cols: transpose(&rock_rows, width),
+        rock_rows,
+        row_segments: Rc::new(wall_rows.iter().map(|&w| segments(w, width)).collect()),
+        col_segments: Rc::new(wall_cols.iter().map(|&w| segments(w, height)).collect()),
+        wall_rows: Rc::new(wall_rows),
+    }
+}
+
+fn part1(file: &str) -> u32 {
+    let mut platform = parse(file);
+    platform.tilt(Direction::North);
+    platform.load(Direction::North)
+}
+
+fn part2(file: &str) -> u32 {
+    let platform = parse(file);
+    let spin = [
+        Direction::North,
+        Direction::West,
+        Direction::South,
+        Direction::East,
+    ];
+    run_repeated(&platform, &spin, 1_000_000_000).load(Direction::North)
+}
+
+// runs any tilt program any number of times, then prints the platform and
+// the load on every edge
+fn explore(file: &str, program: &str, times: u64) {
+    let platform = run_repeated(&parse(file), &parse_program(program), times);
+    print!("{}", platform.render());
+    for edge in [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ] {
+        println!("{:?} load: {}", edge, platform.load(edge));
     }
-    ret_load
 }
 
 // borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
@@ -0,0 +1,198 @@
+// the HASHMAP from the puzzle as an actual map: 256 boxes picked by the
+// HASH of the key, each holding its entries in the order they went in.
+// replacing an entry keeps its slot, removing one closes the gap.
+
+use std::borrow::Borrow;
+use std::hash::Hasher;
+
+const NUM_BOXES: usize = 256;
+
+// the HASH algorithm: for every byte, add it, multiply by 17 and keep the
+// remainder mod 256
+#[derive(Default)]
+pub struct HolidayHasher {
+    state: u8,
+}
+
+impl Hasher for HolidayHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.state = ((self.state as u32 + b as u32) * 17 % 256) as u8;
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.state as u64
+    }
+}
+
+// keys get fed to the hasher as raw bytes rather than through `Hash`, since
+// `str`'s `Hash` impl tacks an extra byte on the end
+pub fn hash<Q: AsRef<[u8]> + ?Sized>(key: &Q) -> u8 {
+    let mut hasher = HolidayHasher::default();
+    hasher.write(key.as_ref());
+    hasher.finish() as u8
+}
+
+pub struct LensMap<K, V> {
+    boxes: Vec<Vec<(K, V)>>,
+}
+
+impl<K, V> LensMap<K, V>
+where
+    K: AsRef<[u8]> + Eq,
+{
+    pub fn new() -> LensMap<K, V> {
+        LensMap {
+            boxes: (0..NUM_BOXES).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    fn slot<Q>(&self, key: &Q) -> (usize, Option<usize>)
+    where
+        K: Borrow<Q>,
+        Q: AsRef<[u8]> + Eq + ?Sized,
+    {
+        let box_num = hash(key) as usize;
+        let pos = self.boxes[box_num]
+            .iter()
+            .position(|(k, _)| k.borrow() == key);
+        (box_num, pos)
+    }
+
+    // puts the value in the key's box, replacing (and returning) any value
+    // it already had there without moving it
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.slot(&key) {
+            (box_num, Some(pos)) => Some(std::mem::replace(&mut self.boxes[box_num][pos].1, value)),
+            (box_num, None) => {
+                self.boxes[box_num].push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: AsRef<[u8]> + Eq + ?Sized,
+    {
+        let (box_num, pos) = self.slot(key);
+        let (_, value) = self.boxes[box_num].remove(pos?);
+        Some(value)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: AsRef<[u8]> + Eq + ?Sized,
+    {
+        let (box_num, pos) = self.slot(key);
+        pos.map(|p| &self.boxes[box_num][p].1)
+    }
+
+    // every entry as (box number, slot in the box, key, value), going
+    // through the boxes in order
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &K, &V)> {
+        self.boxes.iter().enumerate().flat_map(|(box_num, b)| {
+            b.iter()
+                .enumerate()
+                .map(move |(slot, (k, v))| (box_num, slot, k, v))
+        })
+    }
+}
+
+impl<K, V> LensMap<K, V>
+where
+    K: AsRef<[u8]> + Eq,
+    V: Copy + Into<u64>,
+{
+    // the sum of (box number + 1) * (slot + 1) * focal length over every lens
+    pub fn focusing_power(&self) -> u64 {
+        self.iter()
+            .map(|(box_num, slot, _, &focal_length)| {
+                (box_num as u64 + 1) * (slot as u64 + 1) * focal_length.into()
+            })
+            .sum()
+    }
+}
+
+impl<K, V> Default for LensMap<K, V>
+where
+    K: AsRef<[u8]> + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(map: &LensMap<&'static str, u32>) -> Vec<(usize, usize, &'static str, u32)> {
+        map.iter().map(|(b, s, &k, &v)| (b, s, k, v)).collect()
+    }
+
+    #[test]
+    fn hashes_like_the_puzzle() {
+        assert_eq!(hash("HASH"), 52);
+        assert_eq!(hash("rn"), 0);
+        assert_eq!(hash("cm"), 0);
+        assert_eq!(hash("qp"), 1);
+    }
+
+    #[test]
+    fn replacing_keeps_the_slot() {
+        let mut map = LensMap::new();
+        assert_eq!(map.insert("rn", 1), None);
+        assert_eq!(map.insert("cm", 2), None);
+        assert_eq!(map.insert("rn", 5), Some(1));
+        assert_eq!(entries(&map), vec![(0, 0, "rn", 5), (0, 1, "cm", 2)]);
+    }
+
+    #[test]
+    fn removing_closes_the_gap() {
+        let mut map = LensMap::new();
+        map.insert("rn", 1);
+        map.insert("cm", 2);
+        map.insert("qp", 3);
+        assert_eq!(map.remove("rn"), Some(1));
+        assert_eq!(map.remove("rn"), None);
+        assert_eq!(entries(&map), vec![(0, 0, "cm", 2), (1, 0, "qp", 3)]);
+    }
+
+    #[test]
+    fn looking_up_keys() {
+        let mut map = LensMap::new();
+        map.insert("rn", 1);
+        map.insert("cm", 2);
+        assert_eq!(map.get("cm"), Some(&2));
+        assert_eq!(map.get("qp"), None);
+        map.remove("cm");
+        assert_eq!(map.get("cm"), None);
+        assert_eq!(map.get("rn"), Some(&1));
+    }
+
+    #[test]
+    fn iterates_box_by_box() {
+        let mut map = LensMap::new();
+        map.insert("qp", 3);
+        map.insert("rn", 1);
+        map.insert("HASH", 4);
+        map.insert("cm", 2);
+        let keys: Vec<&str> = entries(&map).into_iter().map(|e| e.2).collect();
+        assert_eq!(keys, vec!["rn", "cm", "qp", "HASH"]);
+    }
+
+    #[test]
+    fn focusing_power_of_the_example() {
+        let mut map: LensMap<&str, u32> = LensMap::new();
+        map.insert("rn", 1);
+        map.insert("cm", 2);
+        map.insert("ot", 7);
+        map.insert("ab", 5);
+        map.insert("pc", 6);
+        assert_eq!(map.focusing_power(), 1 + 4 + 28 + 40 + 72);
+    }
+}
@@ -1,13 +1,29 @@
+mod lens_map;
+
 use std::env;
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::Path;
+use std::fmt;
+use std::fs::read_to_string;
+use std::process;
 use std::time::Instant;
 
+use lens_map::{hash, LensMap};
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let file = &args[1];
+    if args.get(2).is_some_and(|a| a == "--lookup") {
+        // day15 <file> --lookup <label>...
+        let lenses = arrange(load_steps(file));
+        for label in &args[3..] {
+            match lenses.get(label.as_str()) {
+                Some(focal_length) => println!("{}: {}", label, focal_length),
+                None => println!("{}: not in any box", label),
+            }
+        }
+        return;
+    }
+
     let start1 = Instant::now();
     let p1 = part1(file);
     let duration1 = start1.elapsed();
@@ -19,87 +35,188 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-fn hash(s: &str, include_special: bool) -> u8 {
-    let mut val = 0;
-    for c in s.chars() {
-        if !include_special && (c == '-' || c == '=') {
-            break;
-        }
-        let mut tmp = val as u32;
-        tmp += c as u32;
-        tmp *= 17;
-        val = (tmp % 256) as u8;
+#[derive(Debug, PartialEq, Eq)]
+enum Operation {
+    Remove,
+    Insert(u32),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Step {
+    label: String,
+    operation: Operation,
+}
+
+#[derive(Debug)]
+struct StepError {
+    index: usize,
+    text: String,
+    reason: &'static str,
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {} ({:?}): {}", self.index, self.text, self.reason)
     }
-    val
 }
 
-fn part1(file: &str) -> u32 {
-    let l = read_lines(file).unwrap().next().unwrap().unwrap();
-    let mut sum: u32 = 0;
-    for step in l.split(',') {
-        sum += hash(step, true) as u32;
+// the whole initialisation sequence as one string of comma separated steps.
+// lines can wrap anywhere, so line breaks are dropped, and so is any
+// whitespace around a step. a trailing comma doesn't make an extra step
+fn read_steps(file: &str) -> Vec<String> {
+    split_steps(&read_to_string(file).unwrap())
+}
+
+fn split_steps(contents: &str) -> Vec<String> {
+    let sequence: String = contents
+        .chars()
+        .filter(|&c| c != '\n' && c != '\r')
+        .collect();
+    let mut steps: Vec<String> = sequence.split(',').map(|s| s.trim().to_string()).collect();
+    if steps.last().is_some_and(|s| s.is_empty()) {
+        steps.pop();
     }
-    sum
+    steps
 }
 
-#[derive(Clone, Debug)]
-struct Lens {
-    label: String,
-    focal_length: Option<u8>,
+// label followed by either - or = and a focal length
+fn parse_step(index: usize, text: &str) -> Result<Step, StepError> {
+    let error = |reason| StepError {
+        index,
+        text: text.to_string(),
+        reason,
+    };
+    let Some(op_index) = text.find(['-', '=']) else {
+        return Err(error("no - or ="));
+    };
+    let (label, rest) = text.split_at(op_index);
+    if label.is_empty() {
+        return Err(error("empty label"));
+    }
+    if !label.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(error("label can only have letters and digits"));
+    }
+    let operation = match (&rest[..1], &rest[1..]) {
+        ("-", "") => Operation::Remove,
+        ("-", _) => return Err(error("unexpected text after -")),
+        (_, "") => return Err(error("missing focal length")),
+        (_, n) if n.chars().all(|c| c.is_ascii_digit()) => Operation::Insert(
+            n.parse::<u32>()
+                .map_err(|_| error("focal length is too big"))?,
+        ),
+        _ => return Err(error("focal length isn't a number")),
+    };
+    Ok(Step {
+        label: label.to_string(),
+        operation,
+    })
 }
 
-fn part2(file: &str) -> u32 {
-    let l = read_lines(file).unwrap().next().unwrap().unwrap();
-    let mut boxes: Vec<Vec<Lens>> = vec![Vec::new(); 256];
-    for step in l.split(',') {
-        let chars: Vec<char> = step.chars().collect();
-        let instruction = if step.ends_with('-') { '-' } else { '=' };
-        let focal_length: Option<u8>;
-        let label: String;
-        if instruction == '-' {
-            focal_length = None;
-            label = chars[..chars.len() - 1].iter().collect();
-        } else {
-            focal_length = Some((chars[chars.len() - 1] as u32 - '0' as u32) as u8);
-            label = chars[..chars.len() - 2].iter().collect();
-        }
-        let lens = Lens {
-            label: label,
-            focal_length: focal_length,
-        };
-        let box_num = hash(step, false);
-        if instruction == '-' {
-            if let Some(pos) = boxes[box_num as usize]
-                .iter()
-                .position(|v| v.label == lens.label)
-            {
-                boxes[box_num as usize].remove(pos);
+fn parse_steps(file: &str) -> Result<Vec<Step>, StepError> {
+    parse_sequence(&read_to_string(file).unwrap())
+}
+
+fn parse_sequence(contents: &str) -> Result<Vec<Step>, StepError> {
+    split_steps(contents)
+        .iter()
+        .enumerate()
+        .map(|(i, text)| parse_step(i, text))
+        .collect()
+}
+
+fn part1(file: &str) -> u32 {
+    read_steps(file).iter().map(|step| hash(step) as u32).sum()
+}
+
+fn load_steps(file: &str) -> Vec<Step> {
+    parse_steps(file).unwrap_or_else(|e| {
+        eprintln!("bad step: {}", e);
+        process::exit(1);
+    })
+}
+
+// runs every step, leaving the lenses where they end up
+fn arrange(steps: Vec<Step>) -> LensMap<String, u32> {
+    let mut lenses: LensMap<String, u32> = LensMap::new();
+    for step in steps {
+        match step.operation {
+            Operation::Remove => {
+                lenses.remove(step.label.as_str());
             }
-        } else {
-            if let Some(pos) = boxes[box_num as usize]
-                .iter()
-                .position(|v| v.label == lens.label)
-            {
-                boxes[box_num as usize][pos] = lens;
-            } else {
-                boxes[box_num as usize].push(lens);
+            Operation::Insert(focal_length) => {
+                lenses.insert(step.label, focal_length);
             }
         }
     }
-    let mut sum: u32 = 0;
-    for (box_num, b) in boxes.iter().enumerate() {
-        for (i, l) in b.iter().enumerate() {
-            sum += ((1 + box_num) * (1 + i) * l.focal_length.unwrap() as usize) as u32;
+    lenses
+}
+
+fn part2(file: &str) -> u64 {
+    arrange(load_steps(file)).focusing_power()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(label: &str, operation: Operation) -> Step {
+        Step {
+            label: label.to_string(),
+            operation,
         }
     }
-    sum
-}
 
-// borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    // the index and reason of the first bad step
+    fn first_error(contents: &str) -> (usize, &'static str) {
+        let e = parse_sequence(contents).unwrap_err();
+        (e.index, e.reason)
+    }
+
+    #[test]
+    fn multi_digit_focal_lengths() {
+        assert_eq!(
+            parse_sequence("rn=12,cm-,qp=305").unwrap(),
+            vec![
+                step("rn", Operation::Insert(12)),
+                step("cm", Operation::Remove),
+                step("qp", Operation::Insert(305)),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapped_lines() {
+        assert_eq!(
+            split_steps("rn=1,c\nm-,\r\nqp=3\n"),
+            vec!["rn=1", "cm-", "qp=3"]
+        );
+        assert_eq!(split_steps("rn=1 , cm-"), vec!["rn=1", "cm-"]);
+    }
+
+    #[test]
+    fn trailing_comma() {
+        assert_eq!(split_steps("rn=1,cm-,\n"), vec!["rn=1", "cm-"]);
+        assert_eq!(first_error("rn=1,,cm-"), (1, "no - or ="));
+    }
+
+    #[test]
+    fn bad_steps() {
+        assert_eq!(first_error("rn=1,cm"), (1, "no - or ="));
+        assert_eq!(first_error("=1"), (0, "empty label"));
+        assert_eq!(
+            first_error("rn=1,cm-,a b=3"),
+            (2, "label can only have letters and digits")
+        );
+        assert_eq!(first_error("rn-1"), (0, "unexpected text after -"));
+        assert_eq!(first_error("rn=1,qp="), (1, "missing focal length"));
+        assert_eq!(
+            first_error("rn=99999999999"),
+            (0, "focal length is too big")
+        );
+        assert_eq!(first_error("rn=1,qp=x"), (1, "focal length isn't a number"));
+        assert_eq!(
+            first_error("rn=1,qp= 3"),
+            (1, "focal length isn't a number")
+        );
+    }
 }
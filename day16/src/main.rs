@@ -1,14 +1,39 @@
-use std::collections::{HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
 use std::time::Instant;
 
+use rayon::prelude::*;
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let file = &args[1];
+    if args.get(2).is_some_and(|a| a == "--brute-force") {
+        let start = Instant::now();
+        let best = best_start_brute_force(&Contraption::parse(file));
+        println!("part2 (brute force): {}, time {:?}", best, start.elapsed());
+        return;
+    }
+    if args.get(2).is_some_and(|a| a == "--trace") {
+        let contraption = Contraption::parse(file);
+        let graph = BeamGraph::new(&contraption);
+        let t = trace(&contraption, &graph, (0, 0, Direction::Right));
+        println!("energized: {}", t.energized);
+        for (r, c, d) in t.exits {
+            println!("exits at ({}, {}) going {:?}", r, c, d);
+        }
+        for (r, c) in t.absorbed {
+            println!("absorbed at ({}, {})", r, c);
+        }
+        println!("loops: {}", t.loops);
+        return;
+    }
+
     let start1 = Instant::now();
     let p1 = part1(file);
     let duration1 = start1.elapsed();
@@ -20,15 +45,7 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-fn parse(file: &str) -> Vec<Vec<char>> {
-    let mut grid: Vec<Vec<char>> = Vec::new();
-    for l in read_lines(file).unwrap() {
-        grid.push(l.unwrap().chars().collect());
-    }
-    grid
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Direction {
     Right,
     Down,
@@ -36,7 +53,7 @@ enum Direction {
     Up,
 }
 
-fn next_tile(coord: (usize, usize), d: Direction, grid: &Vec<Vec<char>>) -> Option<(usize, usize)> {
+fn next_tile(coord: (usize, usize), d: Direction, grid: &[Vec<char>]) -> Option<(usize, usize)> {
     let next_tile: (isize, isize) = match d {
         Direction::Right => (coord.0 as isize, coord.1 as isize + 1),
         Direction::Down => (coord.0 as isize + 1, coord.1 as isize),
@@ -55,107 +72,417 @@ fn next_tile(coord: (usize, usize), d: Direction, grid: &Vec<Vec<char>>) -> Opti
     }
 }
 
-fn next_tiles(
-    coord: (usize, usize),
-    d: Direction,
-    grid: &Vec<Vec<char>>,
-) -> Vec<(usize, usize, Direction)> {
-    let mut tiles: Vec<(usize, usize, Direction)> = Vec::new();
-
-    let char = grid[coord.0][coord.1];
-    if char == '.' {
-        if let Some(t) = next_tile(coord, d, grid) {
-            tiles.push((t.0, t.1, d));
-        }
-    } else if char == '/' || char == '\\' {
-        let next_direction = match (d, char) {
-            (Direction::Right, '/') => Direction::Up,
-            (Direction::Down, '/') => Direction::Left,
-            (Direction::Left, '/') => Direction::Down,
-            (Direction::Up, '/') => Direction::Right,
-            (Direction::Right, '\\') => Direction::Down,
-            (Direction::Down, '\\') => Direction::Right,
-            (Direction::Left, '\\') => Direction::Up,
-            (Direction::Up, '\\') => Direction::Left,
-            _ => panic!("unexpected char"),
-        };
-        if let Some(t) = next_tile(coord, next_direction, grid) {
-            tiles.push((t.0, t.1, next_direction));
-        }
-    } else if char == '|' || char == '-' {
-        let next_directions = match (d, char) {
-            (Direction::Right, '|') => vec![Direction::Up, Direction::Down],
-            (Direction::Down, '|') => vec![Direction::Down],
-            (Direction::Left, '|') => vec![Direction::Up, Direction::Down],
-            (Direction::Up, '|') => vec![Direction::Up],
-            (Direction::Right, '-') => vec![Direction::Right],
-            (Direction::Down, '-') => vec![Direction::Left, Direction::Right],
-            (Direction::Left, '-') => vec![Direction::Left],
-            (Direction::Up, '-') => vec![Direction::Left, Direction::Right],
-            _ => panic!("unexpected char"),
-        };
-        for d in next_directions {
-            if let Some(t) = next_tile(coord, d, grid) {
-                tiles.push((t.0, t.1, d));
+// how each kind of tile treats a beam, as incoming:outgoing pairs of
+// directions (R, D, L, U). a direction that isn't listed carries straight
+// on, and one with nothing after the colon gets absorbed. digits are
+// portals on top of this: a beam going into one comes out of the other tile
+// with the same digit, still heading the same way
+const TILE_RULES: [(char, &str); 11] = [
+    ('.', ""),
+    ('/', "R:U D:L L:D U:R"),
+    ('\\', "R:D D:R L:U U:L"),
+    ('|', "R:UD L:UD"),
+    ('-', "D:LR U:LR"),
+    // absorbs everything
+    ('#', "R: D: L: U:"),
+    // splits a beam every way except straight back
+    ('+', "R:RUD D:DLR L:LUD U:ULR"),
+    // one-way: beams going against the arrow get absorbed
+    ('>', "L:"),
+    ('<', "R:"),
+    ('v', "U:"),
+    ('^', "D:"),
+];
+
+// the outgoing directions for each incoming direction
+type TileRule = [Vec<Direction>; 4];
+
+fn parse_rule(spec: &str) -> TileRule {
+    let mut rule: TileRule = DIRECTIONS.map(|d| vec![d]);
+    for pair in spec.split_whitespace() {
+        let (incoming, outgoing) = pair.split_once(':').unwrap();
+        let incoming = parse_direction(incoming.chars().next().unwrap());
+        rule[incoming as usize] = outgoing.chars().map(parse_direction).collect();
+    }
+    rule
+}
+
+fn parse_direction(c: char) -> Direction {
+    match c {
+        'R' => Direction::Right,
+        'D' => Direction::Down,
+        'L' => Direction::Left,
+        'U' => Direction::Up,
+        _ => panic!("unknown direction {}", c),
+    }
+}
+
+struct Contraption {
+    grid: Vec<Vec<char>>,
+    rules: HashMap<char, TileRule>,
+    // each portal tile and the tile it sends beams out of
+    portals: HashMap<(usize, usize), (usize, usize)>,
+}
+
+impl Contraption {
+    fn parse(file: &str) -> Contraption {
+        let mut grid: Vec<Vec<char>> = Vec::new();
+        for l in read_lines(file).unwrap() {
+            grid.push(l.unwrap().chars().collect());
+        }
+        let mut rules: HashMap<char, TileRule> = TILE_RULES
+            .iter()
+            .map(|&(c, spec)| (c, parse_rule(spec)))
+            .collect();
+
+        let mut portal_ends: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
+        for (r, row) in grid.iter().enumerate() {
+            for (c, &ch) in row.iter().enumerate() {
+                if ch.is_ascii_digit() {
+                    portal_ends.entry(ch).or_default().push((r, c));
+                } else if !rules.contains_key(&ch) {
+                    panic!("unknown tile {} at ({}, {})", ch, r, c);
+                }
             }
         }
+        let mut portals = HashMap::new();
+        for (ch, ends) in portal_ends {
+            assert!(
+                ends.len() == 2,
+                "portal {} needs exactly two ends, found {:?}",
+                ch,
+                ends
+            );
+            portals.insert(ends[0], ends[1]);
+            portals.insert(ends[1], ends[0]);
+            rules.insert(ch, parse_rule(""));
+        }
+
+        Contraption {
+            grid,
+            rules,
+            portals,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.grid.len()
     }
 
-    tiles
+    fn width(&self) -> usize {
+        self.grid[0].len()
+    }
+
+    fn outgoing(&self, coord: (usize, usize), d: Direction) -> &[Direction] {
+        &self.rules[&self.grid[coord.0][coord.1]][d as usize]
+    }
+
+    // where a beam on `coord` heading `d` ends up next, and which way it's
+    // going when it gets there. beams that leave the grid just disappear
+    fn next_tiles(&self, coord: (usize, usize), d: Direction) -> Vec<(usize, usize, Direction)> {
+        let from = self.portals.get(&coord).copied().unwrap_or(coord);
+        self.outgoing(coord, d)
+            .iter()
+            .filter_map(|&next_d| next_tile(from, next_d, &self.grid).map(|t| (t.0, t.1, next_d)))
+            .collect()
+    }
 }
 
-fn count_energized_tiles(grid: &Vec<Vec<char>>, start: (usize, usize), d: Direction) -> u32 {
-    let mut energized_tiles: HashSet<(usize, usize)> = HashSet::new();
-    let mut seen_tiles: HashSet<(usize, usize, Direction)> = HashSet::new();
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+    Direction::Up,
+];
 
-    let mut beams: VecDeque<(usize, usize, Direction)> = VecDeque::from([(start.0, start.1, d)]);
+// every (tile, direction) a beam can be in is a node, with an edge to
+// wherever the beam goes next. beams that go around in circles are strongly
+// connected components, and squashing those down leaves a DAG where a
+// node's energized tiles are its own plus everything downstream of it
+struct BeamGraph {
+    width: usize,
+    edges: Vec<Vec<usize>>,
+}
 
-    while !beams.is_empty() {
-        let beam = beams.pop_front().unwrap();
-        if seen_tiles.contains(&beam) {
-            continue;
+impl BeamGraph {
+    fn new(contraption: &Contraption) -> BeamGraph {
+        let width = contraption.width();
+        let mut edges = vec![Vec::new(); contraption.height() * width * DIRECTIONS.len()];
+        for r in 0..contraption.height() {
+            for c in 0..width {
+                for d in DIRECTIONS {
+                    edges[state_id(width, r, c, d)] = contraption
+                        .next_tiles((r, c), d)
+                        .into_iter()
+                        .map(|(nr, nc, nd)| state_id(width, nr, nc, nd))
+                        .collect();
+                }
+            }
         }
-        seen_tiles.insert(beam);
-        energized_tiles.insert((beam.0, beam.1));
+        BeamGraph { width, edges }
+    }
+
+    fn tile(&self, state: usize) -> usize {
+        state / DIRECTIONS.len()
+    }
+
+    // tarjan's algorithm, iteratively so big grids don't blow the stack.
+    // only looks at states reachable from `starts`. returns the component
+    // of every state (usize::MAX if unreachable) and the number of
+    // components, numbered so every edge goes from a higher component to a
+    // lower or equal one
+    fn components(&self, starts: &[usize]) -> (Vec<usize>, usize) {
+        let n = self.edges.len();
+        let mut index = vec![usize::MAX; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut component = vec![usize::MAX; n];
+        let mut stack: Vec<usize> = Vec::new();
+        let mut next_index = 0;
+        let mut num_components = 0;
 
-        let next_tiles = next_tiles((beam.0, beam.1), beam.2, &grid);
-        for n in next_tiles {
-            beams.push_back(n);
+        for &start in starts {
+            if index[start] != usize::MAX {
+                continue;
+            }
+            // (state, how many of its edges we've looked at)
+            let mut work: Vec<(usize, usize)> = vec![(start, 0)];
+            while let Some(&mut (v, ref mut edge_i)) = work.last_mut() {
+                if *edge_i == 0 && index[v] == usize::MAX {
+                    index[v] = next_index;
+                    low[v] = next_index;
+                    next_index += 1;
+                    stack.push(v);
+                    on_stack[v] = true;
+                }
+                if let Some(&w) = self.edges[v].get(*edge_i) {
+                    *edge_i += 1;
+                    if index[w] == usize::MAX {
+                        work.push((w, 0));
+                    } else if on_stack[w] {
+                        low[v] = low[v].min(index[w]);
+                    }
+                    continue;
+                }
+
+                work.pop();
+                if let Some(&(parent, _)) = work.last() {
+                    low[parent] = low[parent].min(low[v]);
+                }
+                if low[v] == index[v] {
+                    loop {
+                        let w = stack.pop().unwrap();
+                        on_stack[w] = false;
+                        component[w] = num_components;
+                        if w == v {
+                            break;
+                        }
+                    }
+                    num_components += 1;
+                }
+            }
         }
+        (component, num_components)
     }
 
-    energized_tiles.len() as u32
+    // how many tiles end up energized for a beam starting from each of the
+    // given states, all worked out in one go
+    fn energized_counts(&self, starts: &[usize]) -> Vec<u32> {
+        let (component, num_components) = self.components(starts);
+
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); num_components];
+        for (state, &comp) in component.iter().enumerate() {
+            if comp != usize::MAX {
+                members[comp].push(state);
+            }
+        }
+
+        // tarjan finishes a component only after everything it can reach,
+        // so going up from component 0 means everything downstream is done
+        let words = (self.edges.len() / DIRECTIONS.len()).div_ceil(64);
+        let mut energized: Vec<Vec<u64>> = Vec::with_capacity(num_components);
+        for states in &members {
+            let mut tiles = vec![0u64; words];
+            for &state in states {
+                let tile = self.tile(state);
+                tiles[tile / 64] |= 1 << (tile % 64);
+                for &next in &self.edges[state] {
+                    let next_comp = component[next];
+                    if next_comp != energized.len() {
+                        for (t, n) in tiles.iter_mut().zip(&energized[next_comp]) {
+                            *t |= n;
+                        }
+                    }
+                }
+            }
+            energized.push(tiles);
+        }
+
+        starts
+            .iter()
+            .map(|&s| energized[component[s]].iter().map(|w| w.count_ones()).sum())
+            .collect()
+    }
 }
 
-fn part1(file: &str) -> u32 {
-    let grid = parse(file);
-    count_energized_tiles(&grid, (0, 0), Direction::Right)
+fn state_id(width: usize, r: usize, c: usize, d: Direction) -> usize {
+    (r * width + c) * DIRECTIONS.len() + d as usize
 }
 
-fn part2(file: &str) -> u32 {
-    let grid = parse(file);
+fn part1(file: &str) -> u32 {
+    let contraption = Contraption::parse(file);
+    let graph = BeamGraph::new(&contraption);
+    let start = state_id(graph.width, 0, 0, Direction::Right);
+    graph.energized_counts(&[start])[0]
+}
 
-    // yeah I could do something smarter here with memoizing results across runs
-    // but I've gotta leave in 20 minutes so I'm happy with this
+// every tile on the edge with the direction that points into the grid, in
+// a fixed order so ties always go the same way
+fn edge_starts(contraption: &Contraption) -> Vec<(usize, usize, Direction)> {
+    let (height, width) = (contraption.height(), contraption.width());
     let mut starts: Vec<(usize, usize, Direction)> = Vec::new();
-    for r in 0..grid.len() {
+    for r in 0..height {
         starts.push((r, 0, Direction::Right));
-        starts.push((r, grid[0].len() - 1, Direction::Left));
+        starts.push((r, width - 1, Direction::Left));
     }
-    for c in 0..grid[0].len() {
+    for c in 0..width {
         starts.push((0, c, Direction::Down));
-        starts.push((grid.len() - 1, c, Direction::Up));
+        starts.push((height - 1, c, Direction::Up));
+    }
+    starts
+}
+
+// the start that energizes the most tiles
+struct BestStart {
+    energized: u32,
+    start: (usize, usize, Direction),
+}
+
+impl fmt::Display for BestStart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (r, c, d) = self.start;
+        write!(f, "{} (from ({}, {}) going {:?})", self.energized, r, c, d)
+    }
+}
+
+// picks the biggest count, breaking ties by whichever start came first so
+// the answer doesn't depend on what order the counts were worked out in
+fn best_start(starts: &[(usize, usize, Direction)], counts: &[u32]) -> BestStart {
+    let (i, &energized) = counts
+        .iter()
+        .enumerate()
+        .max_by_key(|&(i, &count)| (count, Reverse(i)))
+        .unwrap();
+    BestStart {
+        energized,
+        start: starts[i],
+    }
+}
+
+// the straightforward way: flood fill from a single start
+fn count_energized_tiles(contraption: &Contraption, start: (usize, usize, Direction)) -> u32 {
+    let width = contraption.width();
+    let mut energized = vec![false; contraption.height() * width];
+    let mut seen = vec![false; contraption.height() * width * DIRECTIONS.len()];
+    let mut beams: Vec<(usize, usize, Direction)> = vec![start];
+    let mut count = 0;
+    while let Some((r, c, d)) = beams.pop() {
+        let state = state_id(width, r, c, d);
+        if seen[state] {
+            continue;
+        }
+        seen[state] = true;
+        if !energized[r * width + c] {
+            energized[r * width + c] = true;
+            count += 1;
+        }
+        beams.extend(contraption.next_tiles((r, c), d));
+    }
+    count
+}
+
+// a separate flood fill for every start, spread across all the cores. good
+// for checking the component version against
+fn best_start_brute_force(contraption: &Contraption) -> BestStart {
+    let starts = edge_starts(contraption);
+    let counts: Vec<u32> = starts
+        .par_iter()
+        .map(|&start| count_energized_tiles(contraption, start))
+        .collect();
+    best_start(&starts, &counts)
+}
+
+fn part2(file: &str) -> BestStart {
+    let contraption = Contraption::parse(file);
+    let graph = BeamGraph::new(&contraption);
+    let starts = edge_starts(&contraption);
+    let states: Vec<usize> = starts
+        .iter()
+        .map(|&(r, c, d)| state_id(graph.width, r, c, d))
+        .collect();
+    best_start(&starts, &graph.energized_counts(&states))
+}
+
+// what happens to the beam from a single start: where bits of it leave
+// the grid, where bits of it get absorbed, and whether any of it goes
+// round in circles forever
+struct Trace {
+    energized: u32,
+    exits: Vec<(usize, usize, Direction)>,
+    absorbed: Vec<(usize, usize)>,
+    loops: bool,
+}
+
+fn trace(contraption: &Contraption, graph: &BeamGraph, start: (usize, usize, Direction)) -> Trace {
+    let width = contraption.width();
+    let mut exits: Vec<(usize, usize, Direction)> = Vec::new();
+    let mut absorbed: Vec<(usize, usize)> = Vec::new();
+    let mut seen = vec![false; contraption.height() * width * DIRECTIONS.len()];
+    let mut beams: Vec<(usize, usize, Direction)> = vec![start];
+    while let Some((r, c, d)) = beams.pop() {
+        let state = state_id(width, r, c, d);
+        if seen[state] {
+            continue;
+        }
+        seen[state] = true;
+        let from = contraption.portals.get(&(r, c)).copied().unwrap_or((r, c));
+        let outgoing = contraption.outgoing((r, c), d);
+        if outgoing.is_empty() {
+            absorbed.push((r, c));
+        }
+        for &next_d in outgoing {
+            if next_tile(from, next_d, &contraption.grid).is_none() {
+                exits.push((from.0, from.1, next_d));
+            }
+        }
+        beams.extend(contraption.next_tiles((r, c), d));
     }
+    exits.sort();
+    exits.dedup();
+    absorbed.sort();
+    absorbed.dedup();
 
-    let mut max = 0;
-    for start in starts {
-        let count = count_energized_tiles(&grid, (start.0, start.1), start.2);
-        if count > max {
-            max = count;
+    // a beam loops if it can get back to a state it's already been in,
+    // which is a component with more than one state or one that points at
+    // itself
+    let start_state = state_id(width, start.0, start.1, start.2);
+    let (component, _) = graph.components(&[start_state]);
+    let mut sizes: HashMap<usize, usize> = HashMap::new();
+    let mut loops = false;
+    for (state, &reached) in seen.iter().enumerate() {
+        if reached {
+            *sizes.entry(component[state]).or_default() += 1;
+            loops |= graph.edges[state].contains(&state);
         }
     }
+    loops |= sizes.values().any(|&size| size > 1);
 
-    max
+    Trace {
+        energized: graph.energized_counts(&[start_state])[0],
+        exits,
+        absorbed,
+        loops,
+    }
 }
 
 // borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
@@ -1,4 +1,5 @@
-use std::collections::{HashMap, VecDeque};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
@@ -9,6 +10,19 @@ fn main() {
     let args: Vec<String> = env::args().collect();
 
     let file = &args[1];
+    if args.get(2).is_some_and(|a| a == "--route") {
+        let grid = parse(file);
+        for (name, min_consecutive, max_consecutive) in
+            [("crucible", 1, 3), ("ultra crucible", 4, 10)]
+        {
+            match min_loss(&grid, min_consecutive, max_consecutive) {
+                Some(route) => println!("{}: {}\n{}\n", name, route.heat_loss, route.render(&grid)),
+                None => println!("{}: no way to the factory\n", name),
+            }
+        }
+        return;
+    }
+
     let start1 = Instant::now();
     let p1 = part1(file);
     let duration1 = start1.elapsed();
@@ -23,125 +37,181 @@ fn main() {
 fn parse(file: &str) -> Vec<Vec<u8>> {
     let mut v = Vec::new();
     for l in read_lines(file).unwrap() {
-        v.push(l.unwrap().chars().map(|c| c as u8 - '0' as u8).collect());
+        v.push(
+            l.unwrap()
+                .chars()
+                .map(|c| c.to_digit(10).unwrap() as u8)
+                .collect(),
+        );
     }
     v
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
-    Up,
-    Right,
-    Down,
-    Left,
+// which way the crucible has to move next. after every straight run it
+// has to turn, so that's all a state needs to remember besides where it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Vertical,
+    Horizontal,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Node {
-    row: usize,
-    col: usize,
-    next_direction: Direction,
+impl Axis {
+    fn turn(self) -> Axis {
+        match self {
+            Axis::Vertical => Axis::Horizontal,
+            Axis::Horizontal => Axis::Vertical,
+        }
+    }
 }
 
-fn min_loss(grid: &Vec<Vec<u8>>, min_consecutive: u8, max_consecutive: u8) -> u32 {
-    let mut min_loss = u32::MAX;
-
-    let mut min_values: HashMap<Node, u32> = HashMap::new();
-
-    let max_r = grid.len() - 1;
-    let max_c = grid[0].len() - 1;
-
-    let mut queue: VecDeque<(Node, u32)> = VecDeque::from([
-        (
-            Node {
-                row: 0,
-                col: 0,
-                next_direction: Direction::Down,
-            },
-            0,
-        ),
-        (
-            Node {
-                row: 0,
-                col: 0,
-                next_direction: Direction::Right,
-            },
-            0,
-        ),
-    ]);
-
-    while !queue.is_empty() {
-        let current = queue.pop_front().unwrap();
-
-        if current.0.row == max_r && current.0.col == max_c {
-            if current.1 < min_loss {
-                min_loss = current.1;
-            }
-            continue;
-        }
+fn state_id(width: usize, r: usize, c: usize, axis: Axis) -> usize {
+    (r * width + c) * 2 + axis as usize
+}
 
-        let mut new_heat_loss = current.1;
-        let dir = current.0.next_direction;
+// the cheapest way to the factory and every tile along it, starting with
+// the top left one
+struct Route {
+    heat_loss: u32,
+    tiles: Vec<(usize, usize)>,
+}
 
-        let mut new_r = current.0.row;
-        let mut new_c = current.0.col;
+impl Route {
+    // the grid with the route drawn on it the same way the puzzle does,
+    // an arrow on every tile it moves onto
+    fn render(&self, grid: &[Vec<u8>]) -> String {
+        let mut out: Vec<Vec<char>> = grid
+            .iter()
+            .map(|row| row.iter().map(|&v| (b'0' + v) as char).collect())
+            .collect();
+        for w in self.tiles.windows(2) {
+            let ((r0, c0), (r1, c1)) = (w[0], w[1]);
+            out[r1][c1] = match (r1.cmp(&r0), c1.cmp(&c0)) {
+                (Ordering::Greater, _) => 'v',
+                (Ordering::Less, _) => '^',
+                (_, Ordering::Greater) => '>',
+                _ => '<',
+            };
+        }
+        out.iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
 
-        for i in 0..max_consecutive {
-            if (dir == Direction::Up && new_r == 0)
-                || (dir == Direction::Down && new_r == max_r)
-                || (dir == Direction::Left && new_c == 0)
-                || (dir == Direction::Right && new_c == max_c)
-            {
-                // hit a wall, can't go that way
-                break;
-            }
-            match dir {
-                Direction::Down => new_r += 1,
-                Direction::Right => new_c += 1,
-                Direction::Up => new_r -= 1,
-                Direction::Left => new_c -= 1,
-            }
-            new_heat_loss += grid[new_r][new_c] as u32;
-            if new_heat_loss > min_loss {
-                break;
-            }
+// dijkstra over (row, col, axis) states. every edge is a straight run of
+// between min_consecutive and max_consecutive tiles along the axis, in
+// either direction, and lands on a state that has to turn
+fn min_loss(grid: &[Vec<u8>], min_consecutive: usize, max_consecutive: usize) -> Option<Route> {
+    let height = grid.len();
+    let width = grid[0].len();
+    let num_states = height * width * 2;
+    let mut best = vec![u32::MAX; num_states];
+    let mut prev = vec![usize::MAX; num_states];
+    let mut done = vec![false; num_states];
+    let mut queue: BinaryHeap<Reverse<(u32, usize)>> = BinaryHeap::new();
+
+    for axis in [Axis::Vertical, Axis::Horizontal] {
+        let s = state_id(width, 0, 0, axis);
+        best[s] = 0;
+        queue.push(Reverse((0, s)));
+    }
 
-            if i + 1 < min_consecutive {
-                continue;
-            }
+    while let Some(Reverse((heat_loss, s))) = queue.pop() {
+        if done[s] {
+            continue;
+        }
+        done[s] = true;
+        let (r, c) = (s / 2 / width, s / 2 % width);
+        if r == height - 1 && c == width - 1 {
+            return Some(Route {
+                heat_loss,
+                tiles: route_tiles(width, &prev, s),
+            });
+        }
 
-            let next_directions = match dir {
-                Direction::Right | Direction::Left => [Direction::Up, Direction::Down],
-                Direction::Up | Direction::Down => [Direction::Left, Direction::Right],
-            };
-            for next_d in next_directions {
-                let n = Node {
-                    row: new_r,
-                    col: new_c,
-                    next_direction: next_d,
-                };
-                let min_seen_value = min_values.get(&n).unwrap_or(&u32::MAX);
-                if new_heat_loss < *min_seen_value {
-                    min_values.insert(n, new_heat_loss);
-                    queue.push_back((n, new_heat_loss));
+        let axis = if s % 2 == 0 {
+            Axis::Vertical
+        } else {
+            Axis::Horizontal
+        };
+        let (dr, dc) = match axis {
+            Axis::Vertical => (1, 0),
+            Axis::Horizontal => (0, 1),
+        };
+        for sign in [1, -1] {
+            let mut new_heat_loss = heat_loss;
+            for i in 1..=max_consecutive as isize {
+                let new_r = r as isize + sign * dr * i;
+                let new_c = c as isize + sign * dc * i;
+                if new_r < 0 || new_c < 0 || new_r >= height as isize || new_c >= width as isize {
+                    // hit a wall, can't go any further
+                    break;
+                }
+                let (new_r, new_c) = (new_r as usize, new_c as usize);
+                new_heat_loss += grid[new_r][new_c] as u32;
+                if (i as usize) < min_consecutive {
+                    continue;
+                }
+                let next = state_id(width, new_r, new_c, axis.turn());
+                if new_heat_loss < best[next] {
+                    best[next] = new_heat_loss;
+                    prev[next] = s;
+                    queue.push(Reverse((new_heat_loss, next)));
                 }
             }
         }
     }
 
-    min_loss
+    None
+}
+
+// walks the predecessors back from the end state and fills in the tiles
+// passed over on each straight run
+fn route_tiles(width: usize, prev: &[usize], end: usize) -> Vec<(usize, usize)> {
+    let tile = |s: usize| (s / 2 / width, s / 2 % width);
+    let mut tiles = vec![tile(end)];
+    let mut s = end;
+    while prev[s] != usize::MAX {
+        let (r0, c0) = tile(prev[s]);
+        let (mut r, mut c) = tile(s);
+        while (r, c) != (r0, c0) {
+            r = if r > r0 {
+                r - 1
+            } else if r < r0 {
+                r + 1
+            } else {
+                r
+            };
+            c = if c > c0 {
+                c - 1
+            } else if c < c0 {
+                c + 1
+            } else {
+                c
+            };
+            tiles.push((r, c));
+        }
+        s = prev[s];
+    }
+    tiles.reverse();
+    tiles
 }
 
 fn part1(file: &str) -> u32 {
     let grid = parse(file);
 
     min_loss(&grid, 1, 3)
+        .expect("no way to the factory")
+        .heat_loss
 }
 
 fn part2(file: &str) -> u32 {
     let grid = parse(file);
 
     min_loss(&grid, 4, 10)
+        .expect("no way to the factory")
+        .heat_loss
 }
 
 // borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
@@ -1,9 +1,12 @@
 use std::env;
 use std::fs::read_to_string;
 use std::iter::zip;
+use std::ops::RangeInclusive;
 use std::path::Path;
 use std::time::Instant;
 
+use num_bigint::{BigInt, Sign};
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
@@ -22,38 +25,62 @@ fn main() {
 // this is basically just a quadratic inequality:
 // x * (T - x) > D
 // which works out to
-// -x^2 + Tx - D > 0
-// so a = -1, b = T, c = -D
-// then add one to the distance since we want to go one unit further
-fn quadratic_roots(race: &Race) -> (f64, f64) {
-    let b24ac_root = f64::sqrt((race.time.pow(2) - (4 * (race.distance + 1))) as f64);
-    let pos = ((-1.0 * race.time as f64) + b24ac_root) / -2.0;
-    let neg = ((-1.0 * race.time as f64) - b24ac_root) / -2.0;
-    (pos, neg)
-}
+// x^2 - Tx + D < 0
+// with roots (T +/- sqrt(T^2 - 4D)) / 2. everything stays in integers so
+// the smooshed part2 numbers don't lose precision going through f64, and
+// the discriminant is a big integer since T^2 doesn't fit in a u128 once T
+// gets past 2^64.
+// returns None if there's no hold time that beats the record.
+fn winning_range(race: &Race) -> Option<RangeInclusive<u128>> {
+    let t = race.time;
+    let d = race.distance;
+    // a distance too big for a u128 is definitely bigger than the record
+    let beats = |x: u128| x.checked_mul(t - x).is_none_or(|dist| dist > d);
 
-fn part1(file: &str) -> u64 {
-    let races = read_file(file, false);
-    let mut mul: u64 = 1;
-    for race in races {
-        let (pos, neg) = quadratic_roots(&race);
+    // a negative discriminant means we never even get close, and zero
+    // means the best we can do is tie the record
+    let disc: BigInt = BigInt::from(t).pow(2) - BigInt::from(d) * 4;
+    if disc.sign() != Sign::Plus {
+        return None;
+    }
+    // sqrt(T^2 - 4D) <= T so this always fits back in a u128
+    let root: u128 = disc.sqrt().try_into().unwrap();
 
-        let options = (neg.floor() - pos.ceil() + 1.0) as u64;
-        mul *= options;
+    // the floor of the square root is within one of the real root, so
+    // nudge it onto the first winning hold time
+    let mut lo = (t - root) / 2;
+    while lo <= t / 2 && !beats(lo) {
+        lo += 1;
+    }
+    while lo > 0 && beats(lo - 1) {
+        lo -= 1;
     }
-    mul
+    // the parabola is symmetric around T/2
+    let hi = t - lo;
+    if lo > hi || !beats(lo) {
+        return None;
+    }
+    Some(lo..=hi)
+}
+
+fn num_ways_to_win(race: &Race) -> u128 {
+    winning_range(race).map_or(0, |r| r.end() - r.start() + 1)
+}
+
+fn part1(file: &str) -> u128 {
+    let races = read_file(file, false);
+    races.iter().map(num_ways_to_win).product()
 }
 
-fn part2(file: &str) -> u64 {
+fn part2(file: &str) -> u128 {
     let races = read_file(file, true);
-    let (pos, neg) = quadratic_roots(&races[0]);
-    (neg.floor() - pos.ceil() + 1.0) as u64
+    num_ways_to_win(&races[0])
 }
 
 #[derive(Debug)]
 struct Race {
-    time: u64,
-    distance: u64,
+    time: u128,
+    distance: u128,
 }
 
 fn read_file<P>(filename: P, smoosh: bool) -> Vec<Race>
@@ -65,22 +92,19 @@ where
 
     if smoosh {
         let time_parts: Vec<&str> = lines[0].split_whitespace().skip(1).collect();
-        let time = time_parts.join("").parse::<u64>().unwrap();
+        let time = time_parts.join("").parse::<u128>().unwrap();
         let distance_parts: Vec<&str> = lines[1].split_whitespace().skip(1).collect();
-        let distance = distance_parts.join("").parse::<u64>().unwrap();
-        vec![Race {
-            time: time,
-            distance: distance,
-        }]
+        let distance = distance_parts.join("").parse::<u128>().unwrap();
+        vec![Race { time, distance }]
     } else {
         let times = lines[0]
             .split_whitespace()
             .skip(1)
-            .map(|s| s.parse::<u64>().unwrap());
+            .map(|s| s.parse::<u128>().unwrap());
         let distances = lines[1]
             .split_whitespace()
             .skip(1)
-            .map(|s| s.parse::<u64>().unwrap());
+            .map(|s| s.parse::<u128>().unwrap());
 
         zip(times, distances)
             .map(|(t, d)| Race {
@@ -90,3 +114,66 @@ where
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // slow but obviously correct
+    fn num_ways_to_win_brute_force(race: &Race) -> u128 {
+        (0..=race.time)
+            .filter(|x| x * (race.time - x) > race.distance)
+            .count() as u128
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        // covers records that can be beaten, tied (zero discriminant) and
+        // are out of reach (negative discriminant), plus time zero
+        for time in 0..=60 {
+            for distance in 0..=1000 {
+                let race = Race { time, distance };
+                assert_eq!(
+                    num_ways_to_win(&race),
+                    num_ways_to_win_brute_force(&race),
+                    "{:?}",
+                    race
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn huge_races() {
+        // T^2 doesn't fit in a u128 for any of these
+        let t: u128 = (1 << 64) + 1;
+        let best = (t / 2) * (t - t / 2);
+        let race = Race {
+            time: t,
+            distance: 1,
+        };
+        assert_eq!(num_ways_to_win(&race), t - 1);
+        let race = Race {
+            time: t,
+            distance: best - 1,
+        };
+        assert_eq!(num_ways_to_win(&race), 2);
+        let race = Race {
+            time: t,
+            distance: best,
+        };
+        assert_eq!(num_ways_to_win(&race), 0);
+
+        let t = u128::MAX;
+        let race = Race {
+            time: t,
+            distance: 0,
+        };
+        assert_eq!(num_ways_to_win(&race), t - 1);
+        let race = Race {
+            time: t,
+            distance: u128::MAX,
+        };
+        assert_eq!(num_ways_to_win(&race), t - 3);
+    }
+}
@@ -21,106 +21,186 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum HandType {
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+#[derive(Debug)]
+struct RuleSet {
+    // card labels from weakest to strongest
+    ranks: Vec<char>,
+    // cards that stand in for whatever makes the best hand
+    wildcards: Vec<char>,
+    hand_size: usize,
+    // hand shapes (group sizes, largest first) from weakest to strongest
+    categories: Vec<Vec<u8>>,
+    // turns a hand's card ranks into the key used to break ties between two
+    // hands of the same category
+    tie_break: fn(&[usize]) -> Vec<usize>,
 }
 
-#[derive(Debug, Eq)]
-struct Hand {
-    cards: [u32; 5],
-    bid: u32,
-    hand_type: HandType,
-}
+impl RuleSet {
+    fn new(ranks: &str, wildcards: &str, hand_size: usize) -> RuleSet {
+        RuleSet {
+            ranks: ranks.chars().collect(),
+            wildcards: wildcards.chars().collect(),
+            hand_size,
+            categories: standard_categories(hand_size),
+            tie_break: in_dealt_order,
+        }
+    }
 
-impl Hand {
-    fn new(cards: [u32; 5], bid: u32) -> Hand {
-        let mut counts: HashMap<u32, u8> = HashMap::new();
-        let mut num_jokers: u8 = 0;
-        for c in cards {
-            if c == 1 {
-                num_jokers += 1;
+    fn part1() -> RuleSet {
+        RuleSet::new("23456789TJQKA", "", 5)
+    }
+
+    fn part2() -> RuleSet {
+        RuleSet::new("J23456789TQKA", "J", 5)
+    }
+
+    fn rank(&self, card: char) -> usize {
+        self.ranks
+            .iter()
+            .position(|&c| c == card)
+            .unwrap_or_else(|| panic!("unknown card {}", card))
+    }
+
+    fn is_wild(&self, rank: usize) -> bool {
+        self.wildcards.contains(&self.ranks[rank])
+    }
+
+    fn category_of(&self, shape: &[u8]) -> usize {
+        self.categories
+            .iter()
+            .position(|c| c == shape)
+            .unwrap_or_else(|| panic!("no category for shape {:?}", shape))
+    }
+
+    // all a hand's category depends on: the sizes of its groups of matching
+    // cards (largest first), not counting wildcards, and how many wildcards
+    // it has
+    fn signature(&self, cards: &[usize]) -> (Vec<u8>, usize) {
+        let mut counts = vec![0; self.ranks.len()];
+        let mut num_wild = 0;
+        for &c in cards {
+            if self.is_wild(c) {
+                num_wild += 1;
             } else {
-                *counts.entry(c).or_insert(0) += 1;
+                counts[c] += 1;
             }
         }
-        // I'm so sorry
-        let mut hand_type = HandType::HighCard;
-        if counts.len() == 1 {
-            hand_type = HandType::FiveOfAKind;
-        } else if counts.values().any(|&v| v == 4) {
-            if num_jokers == 1 {
-                hand_type = HandType::FiveOfAKind;
-            } else {
-                hand_type = HandType::FourOfAKind;
-            }
-        } else if counts.values().any(|&v| v == 3) && counts.values().any(|v| *v == 2) {
-            // not possible for a joker to appear here
-            hand_type = HandType::FullHouse;
-        } else if counts.values().any(|&v| v == 3) {
-            if num_jokers == 2 {
-                hand_type = HandType::FiveOfAKind;
-            } else if num_jokers == 1 {
-                hand_type = HandType::FourOfAKind;
-            } else {
-                hand_type = HandType::ThreeOfAKind;
-            }
-        } else if counts.values().any(|&v| v == 2) {
-            if num_jokers == 1 && counts.len() == 2 {
-                hand_type = HandType::FullHouse;
-            } else if num_jokers == 1 && counts.len() == 3 {
-                hand_type = HandType::ThreeOfAKind;
-            } else if num_jokers == 0 && counts.len() == 3 {
-                hand_type = HandType::TwoPair;
-            } else if num_jokers == 3 {
-                hand_type = HandType::FiveOfAKind;
-            } else if num_jokers == 2 {
-                hand_type = HandType::FourOfAKind;
-            } else if num_jokers == 1 {
-                hand_type = HandType::ThreeOfAKind;
-            } else {
-                hand_type = HandType::OnePair;
+        let mut groups: Vec<u8> = counts.into_iter().filter(|&n| n > 0).collect();
+        groups.sort_by(|a, b| b.cmp(a));
+        (groups, num_wild)
+    }
+
+    // how many different groups the non-wild cards can form
+    fn max_groups(&self) -> usize {
+        (0..self.ranks.len()).filter(|&r| !self.is_wild(r)).count()
+    }
+
+    // figures out the best category a hand can reach by trying every way of
+    // spreading the wildcards across the other cards
+    fn best_category(&self, groups: Vec<u8>, num_wild: usize, max_groups: usize) -> usize {
+        if num_wild == 0 {
+            let mut shape = groups;
+            shape.sort_by(|a, b| b.cmp(a));
+            return self.category_of(&shape);
+        }
+
+        let mut best = None;
+        for i in 0..groups.len() {
+            let mut next = groups.clone();
+            next[i] += 1;
+            best = best.max(Some(self.best_category(next, num_wild - 1, max_groups)));
+        }
+        if groups.len() < max_groups {
+            let mut next = groups;
+            next.push(1);
+            best = best.max(Some(self.best_category(next, num_wild - 1, max_groups)));
+        }
+        best.expect("wildcard with nothing to become")
+    }
+}
+
+// camel cards compares hands card by card in the order they were dealt
+fn in_dealt_order(cards: &[usize]) -> Vec<usize> {
+    cards.to_vec()
+}
+
+// every way of splitting n cards into groups, largest group first
+fn partitions(n: usize) -> Vec<Vec<u8>> {
+    fn build(remaining: u8, max: u8, current: &mut Vec<u8>, out: &mut Vec<Vec<u8>>) {
+        if remaining == 0 {
+            out.push(current.clone());
+            return;
+        }
+        for k in (1..=remaining.min(max)).rev() {
+            current.push(k);
+            build(remaining - k, k, current, out);
+            current.pop();
+        }
+    }
+
+    let mut out = Vec::new();
+    build(n as u8, n as u8, &mut Vec::new(), &mut out);
+    out
+}
+
+// every shape a hand can have, weakest first. comparing the shapes
+// lexicographically gives the usual order (high card < one pair < two pair
+// < three of a kind < ...)
+fn standard_categories(hand_size: usize) -> Vec<Vec<u8>> {
+    let mut categories = partitions(hand_size);
+    categories.sort();
+    categories
+}
+
+// the category of every hand signature under a rule set. there are only a
+// few dozen of them so it's cheap to build, and classifying a hand is just
+// working out its signature and looking it up
+struct CategoryTable {
+    categories: HashMap<(Vec<u8>, usize), usize>,
+}
+
+impl CategoryTable {
+    fn new(rules: &RuleSet) -> CategoryTable {
+        let max_groups = rules.max_groups();
+        let mut categories = HashMap::new();
+        for num_wild in 0..=rules.hand_size {
+            for groups in partitions(rules.hand_size - num_wild) {
+                if groups.len() <= max_groups {
+                    let category = rules.best_category(groups.clone(), num_wild, max_groups);
+                    categories.insert((groups, num_wild), category);
+                }
             }
-        } else if num_jokers == 5 {
-            hand_type = HandType::FiveOfAKind;
-        } else if num_jokers == 4 {
-            hand_type = HandType::FiveOfAKind;
-        } else if num_jokers == 3 {
-            hand_type = HandType::FourOfAKind;
-        } else if num_jokers == 2 {
-            hand_type = HandType::ThreeOfAKind;
-        } else if num_jokers == 1 {
-            hand_type = HandType::OnePair;
         }
+        CategoryTable { categories }
+    }
+
+    fn get(&self, rules: &RuleSet, cards: &[usize]) -> usize {
+        self.categories[&rules.signature(cards)]
+    }
+}
+
+#[derive(Debug, Eq)]
+struct Hand {
+    bid: u32,
+    category: usize,
+    tie_break_key: Vec<usize>,
+}
 
+impl Hand {
+    fn new(cards: Vec<usize>, bid: u32, rules: &RuleSet, table: &CategoryTable) -> Hand {
         Hand {
-            cards: cards,
-            bid: bid,
-            hand_type: hand_type,
+            category: table.get(rules, &cards),
+            tie_break_key: (rules.tie_break)(&cards),
+            bid,
         }
     }
 }
 
 impl Ord for Hand {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.hand_type != other.hand_type {
-            self.hand_type.cmp(&other.hand_type)
-        } else {
-            let mut ordering = Ordering::Equal;
-            for i in 0..self.cards.len() {
-                if self.cards[i] != other.cards[i] {
-                    ordering = self.cards[i].cmp(&other.cards[i]);
-                    break;
-                }
-            }
-            ordering
-        }
+        self.category
+            .cmp(&other.category)
+            .then_with(|| self.tie_break_key.cmp(&other.tie_break_key))
     }
 }
 
@@ -132,39 +212,35 @@ impl PartialOrd for Hand {
 
 impl PartialEq for Hand {
     fn eq(&self, other: &Self) -> bool {
-        self.cards == other.cards
+        self.cmp(other) == Ordering::Equal
     }
 }
 
-fn hands_from_file(file: &str, use_jokers: bool) -> Vec<Hand> {
+fn hands_from_file(file: &str, rules: &RuleSet) -> Vec<Hand> {
+    let table = CategoryTable::new(rules);
     let mut hands: Vec<Hand> = Vec::new();
     for l in read_lines(file).unwrap() {
         let line = l.unwrap();
         let parts: Vec<&str> = line.split_whitespace().collect();
-        let mut cards: [u32; 5] = [0, 0, 0, 0, 0];
-        for (i, c) in parts[0].chars().enumerate() {
-            cards[i] = match c {
-                'T' => 10,
-                'J' => {
-                    if use_jokers {
-                        1
-                    } else {
-                        11
-                    }
-                }
-                'Q' => 12,
-                'K' => 13,
-                'A' => 14,
-                _ => c.to_digit(10).unwrap(),
-            }
-        }
-        hands.push(Hand::new(cards, parts[1].parse::<u32>().unwrap()));
+        let cards: Vec<usize> = parts[0].chars().map(|c| rules.rank(c)).collect();
+        assert_eq!(
+            cards.len(),
+            rules.hand_size,
+            "hand {} has the wrong number of cards",
+            parts[0]
+        );
+        hands.push(Hand::new(
+            cards,
+            parts[1].parse::<u32>().unwrap(),
+            rules,
+            &table,
+        ));
     }
     hands
 }
 
-fn part1(file: &str) -> u32 {
-    let mut hands = hands_from_file(file, false);
+fn total_winnings(file: &str, rules: &RuleSet) -> u32 {
+    let mut hands = hands_from_file(file, rules);
     hands.sort();
 
     let mut sum: u32 = 0;
@@ -174,15 +250,12 @@ fn part1(file: &str) -> u32 {
     sum
 }
 
-fn part2(file: &str) -> u32 {
-    let mut hands = hands_from_file(file, true);
-    hands.sort();
+fn part1(file: &str) -> u32 {
+    total_winnings(file, &RuleSet::part1())
+}
 
-    let mut sum: u32 = 0;
-    for (i, hand) in hands.iter().enumerate() {
-        sum += (i + 1) as u32 * hand.bid;
-    }
-    sum
+fn part2(file: &str) -> u32 {
+    total_winnings(file, &RuleSet::part2())
 }
 
 // borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
@@ -193,3 +266,93 @@ where
     let file = File::open(filename)?;
     Ok(io::BufReader::new(file).lines())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the dumbest possible classifier: swap each wildcard for every other
+    // card and keep whichever hand comes out best. the answer doesn't depend
+    // on the order of the cards, so it's remembered for each sorted hand
+    fn classify_brute_force(
+        rules: &RuleSet,
+        cards: &[usize],
+        memo: &mut HashMap<Vec<usize>, usize>,
+    ) -> usize {
+        let mut sorted = cards.to_vec();
+        sorted.sort();
+        if let Some(&category) = memo.get(&sorted) {
+            return category;
+        }
+        let category = match sorted.iter().position(|&c| rules.is_wild(c)) {
+            Some(i) => (0..rules.ranks.len())
+                .filter(|&r| !rules.is_wild(r))
+                .map(|r| {
+                    let mut substituted = sorted.clone();
+                    substituted[i] = r;
+                    classify_brute_force(rules, &substituted, memo)
+                })
+                .max()
+                .expect("wildcard with nothing to become"),
+            None => {
+                let mut counts = vec![0; rules.ranks.len()];
+                for &c in cards {
+                    counts[c] += 1;
+                }
+                let mut shape: Vec<u8> = counts.into_iter().filter(|&n| n > 0).collect();
+                shape.sort_by(|a, b| b.cmp(a));
+                rules.category_of(&shape)
+            }
+        };
+        memo.insert(sorted, category);
+        category
+    }
+
+    // every possible hand with its cards in sorted order. neither the table
+    // nor the brute force classifier cares what order the cards are in, so
+    // this covers every hand there is
+    fn sorted_hands(num_ranks: usize, hand_size: usize) -> Vec<Vec<usize>> {
+        let mut hands = Vec::new();
+        let mut hand = vec![0; hand_size];
+        loop {
+            hands.push(hand.clone());
+            // bump the last card that can still go up, and everything after
+            // it to match
+            let Some(i) = hand.iter().rposition(|&c| c + 1 < num_ranks) else {
+                return hands;
+            };
+            let next = hand[i] + 1;
+            for c in &mut hand[i..] {
+                *c = next;
+            }
+        }
+    }
+
+    // checks the table against the brute force classifier for every
+    // possible hand
+    fn verify_table(rules: &RuleSet) {
+        let table = CategoryTable::new(rules);
+        let mut memo = HashMap::new();
+        let hands = sorted_hands(rules.ranks.len(), rules.hand_size);
+        // 17 choose 5
+        assert_eq!(hands.len(), 6188);
+        for cards in hands {
+            assert_eq!(
+                table.get(rules, &cards),
+                classify_brute_force(rules, &cards, &mut memo),
+                "hand {} misclassified",
+                cards.iter().map(|&c| rules.ranks[c]).collect::<String>()
+            );
+        }
+    }
+
+    #[test]
+    fn part1_table_matches_brute_force() {
+        verify_table(&RuleSet::part1());
+    }
+
+    #[test]
+    fn part2_table_matches_brute_force() {
+        verify_table(&RuleSet::part2());
+    }
+}
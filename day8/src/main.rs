@@ -20,95 +20,240 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-fn parse(file: &str) -> (HashMap<String, (String, String)>, Vec<char>) {
-    let mut map: HashMap<String, (String, String)> = HashMap::new();
-    let mut directions: Vec<char> = Vec::new();
-    for (i, l) in read_lines(file).unwrap().enumerate() {
-        let line = l.unwrap();
-        if i == 0 {
-            directions = line.chars().collect();
-        } else if !line.is_empty() {
-            map.insert(
-                line[0..3].to_string(),
-                (line[7..10].to_string(), line[12..15].to_string()),
-            );
-        }
-    }
-    (map, directions)
+// the network with every node name interned to an index, so walking it is
+// just indexing into `edges`
+struct Network {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    // (left, right) for each node
+    edges: Vec<[usize; 2]>,
+    // 0 for L and 1 for R, so they index straight into `edges`
+    directions: Vec<usize>,
 }
 
-fn part1(file: &str) -> u32 {
-    let (map, directions) = parse(file);
-
-    let mut node: &String = &"AAA".to_string();
-    let mut steps: u32 = 0;
-    let mut direction_index: usize = 0;
-    while node != "ZZZ" {
-        let dir = directions[direction_index];
-        let map_node = map.get(node).unwrap();
-        let next_node = if dir == 'L' { &map_node.0 } else { &map_node.1 };
-        steps += 1;
-        direction_index = if direction_index == directions.len() - 1 {
-            0
-        } else {
-            direction_index + 1
+impl Network {
+    fn parse(file: &str) -> Network {
+        let mut network = Network {
+            names: Vec::new(),
+            ids: HashMap::new(),
+            edges: Vec::new(),
+            directions: Vec::new(),
         };
-        node = &next_node;
+        for (i, l) in read_lines(file).unwrap().enumerate() {
+            let line = l.unwrap();
+            if i == 0 {
+                network.directions = line
+                    .chars()
+                    .map(|c| match c {
+                        'L' => 0,
+                        'R' => 1,
+                        _ => panic!("unexpected direction {}", c),
+                    })
+                    .collect();
+            } else if !line.is_empty() {
+                let (name, children) = line.split_once(" = ").unwrap();
+                let (left, right) = children
+                    .trim_matches(|c| c == '(' || c == ')')
+                    .split_once(", ")
+                    .unwrap();
+                let node = network.intern(name);
+                let left = network.intern(left);
+                let right = network.intern(right);
+                network.edges[node] = [left, right];
+            }
+        }
+        network
+    }
+
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.edges.push([id, id]);
+        id
+    }
+
+    fn nodes_where<F>(&self, pred: F) -> Vec<usize>
+    where
+        F: Fn(&str) -> bool,
+    {
+        (0..self.names.len())
+            .filter(|&id| pred(&self.names[id]))
+            .collect()
     }
 
-    steps
+    // a lookup table of which nodes the predicate matches so the walks
+    // don't have to look at names at all
+    fn node_mask<F>(&self, pred: F) -> Vec<bool>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.names.iter().map(|n| pred(n)).collect()
+    }
+
+    fn next(&self, node: usize, steps: u64) -> usize {
+        let direction = self.directions[(steps % self.directions.len() as u64) as usize];
+        self.edges[node][direction]
+    }
+
+    // how many steps it takes to get from `start` to any goal node
+    fn steps_to_goal(&self, start: usize, goals: &[bool]) -> u64 {
+        let mut node = start;
+        let mut steps: u64 = 0;
+        while !goals[node] {
+            node = self.next(node, steps);
+            steps += 1;
+        }
+        steps
+    }
+
+    // the first step where ghosts starting from every start node are all on
+    // goal nodes at once, if that ever happens
+    fn ghost_walk<S, G>(&self, is_start: S, is_goal: G) -> Option<u128>
+    where
+        S: Fn(&str) -> bool,
+        G: Fn(&str) -> bool,
+    {
+        let goals = self.node_mask(is_goal);
+        let cycles: Vec<GhostCycle> = self
+            .nodes_where(is_start)
+            .into_iter()
+            .map(|start| GhostCycle::new(self, start, &goals))
+            .collect();
+        first_common_goal(&cycles)
+    }
 }
 
-fn part2(file: &str) -> u64 {
-    let (map, directions) = parse(file);
-    let initial_nodes: Vec<&String> = map.keys().filter(|s| s.ends_with("A")).collect();
-    let mut first_z: Vec<Option<u32>> = vec![None; initial_nodes.len()];
-
-    let mut current_nodes = initial_nodes.clone();
-    let mut steps: u32 = 0;
-    let mut direction_index: usize = 0;
-    while first_z.iter().any(|z| z.is_none()) {
-        let dir = directions[direction_index];
-        for i in 0..current_nodes.len() {
-            let current_node = map.get(current_nodes[i]).unwrap();
-            let next_node = if dir == 'L' {
-                &current_node.0
-            } else {
-                &current_node.1
-            };
-            if first_z[i].is_none() && next_node.ends_with("Z") {
-                first_z[i] = Some(steps + 1);
+fn part1(file: &str) -> u64 {
+    let network = Network::parse(file);
+    let start = network.ids["AAA"];
+    let goals = network.node_mask(|n| n == "ZZZ");
+    network.steps_to_goal(start, &goals)
+}
+
+fn part2(file: &str) -> u128 {
+    let network = Network::parse(file);
+    network
+        .ghost_walk(|n| n.ends_with('A'), |n| n.ends_with('Z'))
+        .expect("ghosts never all reach Z at the same time")
+}
+
+// a ghost's state is its node plus where it is in the directions, so it
+// has to repeat eventually. once it's taken `cycle_start` steps it keeps
+// going around the same loop of `cycle_len` steps forever
+#[derive(Debug)]
+struct GhostCycle {
+    cycle_start: u64,
+    cycle_len: u64,
+    // steps before the loop starts where the ghost is on a goal node
+    goals_before_cycle: Vec<u64>,
+    // steps during the first lap of the loop where the ghost is on a goal node
+    goals_in_cycle: Vec<u64>,
+}
+
+impl GhostCycle {
+    fn new(network: &Network, start: usize, goals: &[bool]) -> GhostCycle {
+        let num_directions = network.directions.len();
+        // when each (node, direction index) state was first reached
+        let mut seen: Vec<Option<u64>> = vec![None; network.names.len() * num_directions];
+        let mut goal_steps: Vec<u64> = Vec::new();
+        let mut node = start;
+        let mut steps: u64 = 0;
+        loop {
+            let state = node * num_directions + (steps % num_directions as u64) as usize;
+            if let Some(cycle_start) = seen[state] {
+                let split = goal_steps.partition_point(|&z| z < cycle_start);
+                let goals_in_cycle = goal_steps.split_off(split);
+                return GhostCycle {
+                    cycle_start,
+                    cycle_len: steps - cycle_start,
+                    goals_before_cycle: goal_steps,
+                    goals_in_cycle,
+                };
             }
-            current_nodes[i] = next_node;
+            seen[state] = Some(steps);
+            if goals[node] {
+                goal_steps.push(steps);
+            }
+
+            node = network.next(node, steps);
+            steps += 1;
         }
-        steps += 1;
-        direction_index = if direction_index == directions.len() - 1 {
-            0
+    }
+
+    fn is_goal_at(&self, steps: u64) -> bool {
+        if steps < self.cycle_start {
+            self.goals_before_cycle.binary_search(&steps).is_ok()
         } else {
-            direction_index + 1
-        };
+            let lap_step = self.cycle_start + (steps - self.cycle_start) % self.cycle_len;
+            self.goals_in_cycle.binary_search(&lap_step).is_ok()
+        }
+    }
+}
+
+// the first step where every ghost is on a goal node at once, if there is one.
+// before every ghost has reached its loop we just check each step directly;
+// after that each ghost is on a goal node exactly when the step count lands on
+// one of its goal residues, so it's a chinese remainder problem
+fn first_common_goal(cycles: &[GhostCycle]) -> Option<u128> {
+    let all_looping = cycles.iter().map(|c| c.cycle_start).max().unwrap_or(0);
+    if let Some(steps) = (0..all_looping).find(|&s| cycles.iter().all(|c| c.is_goal_at(s))) {
+        return Some(steps as u128);
+    }
+
+    // every combination of goal residues that's consistent across the ghosts
+    // seen so far, as (remainder, modulus)
+    let mut congruences: Vec<(i128, i128)> = vec![(0, 1)];
+    for cycle in cycles {
+        let len = cycle.cycle_len as i128;
+        let mut next = Vec::new();
+        for &(r, m) in &congruences {
+            for &z in &cycle.goals_in_cycle {
+                if let Some(combined) = crt(r, m, z as i128 % len, len) {
+                    next.push(combined);
+                }
+            }
+        }
+        next.sort();
+        next.dedup();
+        congruences = next;
     }
 
-    let unwrapped: Vec<u64> = first_z.iter().map(|z| z.unwrap() as u64).collect();
-    lcm(&unwrapped)
+    let all_looping = all_looping as i128;
+    congruences
+        .into_iter()
+        .map(|(r, m)| {
+            // the smallest step >= all_looping that's congruent to r
+            all_looping + (r - all_looping).rem_euclid(m)
+        })
+        .min()
+        .map(|steps| steps as u128)
 }
 
-// too annoyed by this problem to write my own lcm so I stole one from
-// https://github.com/TheAlgorithms/Rust/blob/master/src/math/lcm_of_n_numbers.rs
-fn lcm(nums: &[u64]) -> u64 {
-    if nums.len() == 1 {
-        return nums[0];
+// generalised chinese remainder theorem: combines x = a1 (mod m1) and
+// x = a2 (mod m2) into a single congruence, if they're compatible. the
+// moduli don't need to be coprime
+fn crt(a1: i128, m1: i128, a2: i128, m2: i128) -> Option<(i128, i128)> {
+    let (g, p, _) = extended_gcd(m1, m2);
+    if (a2 - a1) % g != 0 {
+        return None;
     }
-    let a = nums[0];
-    let b = lcm(&nums[1..]);
-    a * b / gcd_of_two_numbers(a, b)
+    let lcm = m1 / g * m2;
+    // m1 * p = g (mod m2), so stepping a1 by m1 * p * (a2 - a1) / g lands on a2
+    let k = ((a2 - a1) / g % (m2 / g)) * (p % (m2 / g)) % (m2 / g);
+    Some(((a1 + m1 * k).rem_euclid(lcm), lcm))
 }
 
-fn gcd_of_two_numbers(a: u64, b: u64) -> u64 {
+// returns (g, x, y) such that a * x + b * y = g = gcd(a, b)
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
     if b == 0 {
-        return a;
+        return (a, 1, 0);
     }
-    gcd_of_two_numbers(b, a % b)
+    let (g, x, y) = extended_gcd(b, a % b);
+    (g, y, x - (a / b) * y)
 }
 
 // borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
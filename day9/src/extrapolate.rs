@@ -0,0 +1,211 @@
+use std::fmt;
+
+// predicts values of a sequence without building the whole difference
+// pyramid. if the differences bottom out at zero after d rows the sequence
+// is a degree d polynomial, and newton's forward difference formula gives
+// the value at any index k straight from the first value of each row:
+//
+// f(k) = sum over j of C(k, j) * (j-th difference at index 0)
+//
+// C(k, j) is the generalised binomial coefficient, which works for negative
+// k too, so going backwards is the same as going forwards.
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExtrapolateError {
+    // no row of the difference pyramid was all zeros, so there's no
+    // polynomial we can trust to extend the sequence
+    NeverSettles,
+    // the answer (or something on the way to it) doesn't fit in an i128
+    Overflow,
+}
+
+impl fmt::Display for ExtrapolateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtrapolateError::NeverSettles => {
+                write!(f, "differences never settle to zero, need more values")
+            }
+            ExtrapolateError::Overflow => write!(f, "too big to fit in an i128"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Sequence {
+    // the first value of each row of the difference pyramid, down to the
+    // last row that isn't all zeros
+    leading_differences: Vec<i128>,
+    // how many values the sequence was fitted to
+    len: usize,
+}
+
+impl Sequence {
+    pub fn fit(values: &[i128]) -> Result<Sequence, ExtrapolateError> {
+        // each pass turns row j of the pyramid into row j + 1 in place, so
+        // once pass j is done values[j] is the first value of row j
+        let len = values.len();
+        let mut rows = values.to_vec();
+        for row in 0..rows.len() {
+            if rows[row..].iter().all(|&v| v == 0) {
+                rows.truncate(row);
+                return Ok(Sequence {
+                    leading_differences: rows,
+                    len,
+                });
+            }
+            for i in (row + 1..rows.len()).rev() {
+                rows[i] = rows[i]
+                    .checked_sub(rows[i - 1])
+                    .ok_or(ExtrapolateError::Overflow)?;
+            }
+        }
+        Err(ExtrapolateError::NeverSettles)
+    }
+
+    // the value at index k, where the first value given to `fit` is index 0
+    pub fn at(&self, k: i128) -> Result<i128, ExtrapolateError> {
+        let mut value: i128 = 0;
+        // C(k, 0)
+        let mut binomial: i128 = 1;
+        for (j, &diff) in self.leading_differences.iter().enumerate() {
+            if j > 0 {
+                binomial = next_binomial(binomial, k, j as i128)?;
+            }
+            let term = binomial
+                .checked_mul(diff)
+                .ok_or(ExtrapolateError::Overflow)?;
+            value = value.checked_add(term).ok_or(ExtrapolateError::Overflow)?;
+        }
+        Ok(value)
+    }
+
+    // the next n values after the ones the sequence was fitted to
+    pub fn next_values(&self, n: usize) -> Result<Vec<i128>, ExtrapolateError> {
+        let len = self.len as i128;
+        (len..len + n as i128).map(|k| self.at(k)).collect()
+    }
+
+    // the degree of the fitted polynomial. a sequence of all zeros counts as
+    // degree 0
+    pub fn degree(&self) -> usize {
+        self.leading_differences.len().saturating_sub(1)
+    }
+
+    // the fitted polynomial's coefficients in k, constant term first. the
+    // newton form is a sum of falling factorials k(k-1)...(k-j+1) / j!, so
+    // this just multiplies those out
+    pub fn coefficients(&self) -> Result<Vec<Rational>, ExtrapolateError> {
+        let mut coefficients = vec![Rational::from(0); self.degree() + 1];
+        // the falling factorial for the current j, constant term first
+        let mut falling: Vec<i128> = vec![1];
+        let mut factorial: i128 = 1;
+        for (j, &diff) in self.leading_differences.iter().enumerate() {
+            for (c, &f) in coefficients.iter_mut().zip(&falling) {
+                let term = Rational::new(
+                    f.checked_mul(diff).ok_or(ExtrapolateError::Overflow)?,
+                    factorial,
+                );
+                *c = c.checked_add(term).ok_or(ExtrapolateError::Overflow)?;
+            }
+            // multiply by (k - j) to get the next falling factorial
+            let mut next = vec![0; falling.len() + 1];
+            for (i, &f) in falling.iter().enumerate() {
+                next[i + 1] += f;
+                next[i] -= f.checked_mul(j as i128).ok_or(ExtrapolateError::Overflow)?;
+            }
+            falling = next;
+            factorial = factorial
+                .checked_mul(j as i128 + 1)
+                .ok_or(ExtrapolateError::Overflow)?;
+        }
+        Ok(coefficients)
+    }
+}
+
+// an exact fraction, always kept in lowest terms with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    pub fn new(num: i128, den: i128) -> Rational {
+        assert!(den != 0, "zero denominator");
+        let g = gcd(num, den) * den.signum();
+        Rational {
+            num: num / g,
+            den: den / g,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    fn checked_add(self, other: Rational) -> Option<Rational> {
+        let num = self
+            .num
+            .checked_mul(other.den)?
+            .checked_add(other.num.checked_mul(self.den)?)?;
+        Some(Rational::new(num, self.den.checked_mul(other.den)?))
+    }
+}
+
+impl From<i128> for Rational {
+    fn from(n: i128) -> Rational {
+        Rational { num: n, den: 1 }
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+// C(k, j) from C(k, j - 1), which is C(k, j - 1) * (k - j + 1) / j. the
+// division is always exact, and dividing out the common factor first means
+// this only overflows when C(k, j) itself doesn't fit
+fn next_binomial(prev: i128, k: i128, j: i128) -> Result<i128, ExtrapolateError> {
+    let top = k.checked_sub(j - 1).ok_or(ExtrapolateError::Overflow)?;
+    let g = gcd(prev, j);
+    (prev / g)
+        .checked_mul(top / (j / g))
+        .ok_or(ExtrapolateError::Overflow)
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn far_ahead() {
+        let sequence = Sequence::fit(&[0, 1, 2]).unwrap();
+        assert_eq!(sequence.at(1 << 64), Ok(1 << 64));
+        let squares = Sequence::fit(&[0, 1, 4, 9]).unwrap();
+        assert_eq!(squares.at(1 << 62), Ok(1 << 124));
+        assert_eq!(squares.at(-(1 << 62)), Ok(1 << 124));
+        assert_eq!(squares.at(1 << 64), Err(ExtrapolateError::Overflow));
+    }
+
+    #[test]
+    fn close_by() {
+        let sequence = Sequence::fit(&[10, 13, 16, 21, 30, 45]).unwrap();
+        assert_eq!(sequence.at(6), Ok(68));
+        assert_eq!(sequence.at(-1), Ok(5));
+        assert_eq!(sequence.next_values(2), Ok(vec![68, 101]));
+    }
+}
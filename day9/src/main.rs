@@ -1,13 +1,23 @@
+mod extrapolate;
+
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::process;
 use std::time::Instant;
 
+use extrapolate::Sequence;
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let file = &args[1];
+    if args.get(2).is_some_and(|a| a == "--analyse") {
+        analyse(file);
+        return;
+    }
+
     let start1 = Instant::now();
     let p1 = part1(file);
     let duration1 = start1.elapsed();
@@ -19,54 +29,81 @@ fn main() {
     println!("part2: {}, time {:?}", p2, duration2);
 }
 
-fn parse_histories(file: &str) -> Vec<Vec<Vec<i32>>> {
-    let mut all_histories: Vec<Vec<Vec<i32>>> = Vec::new();
-    for l in read_lines(file).unwrap() {
+// each history along with the line it came from
+fn parse_histories(file: &str) -> Vec<(usize, Vec<i128>)> {
+    let mut histories: Vec<(usize, Vec<i128>)> = Vec::new();
+    for (i, l) in read_lines(file).unwrap().enumerate() {
         let line = l.unwrap();
-        let vals: Vec<i32> = line
-            .split_whitespace()
-            .map(|s| s.parse::<i32>().unwrap())
-            .collect();
-        let mut histories = vec![vals];
-        while histories[histories.len() - 1].iter().any(|&v| v != 0) {
-            let curr_last = &histories[histories.len() - 1];
-            let mut new_last: Vec<i32> = Vec::new();
-            for i in 1..curr_last.len() {
-                new_last.push(curr_last[i] - curr_last[i - 1]);
-            }
-            histories.push(new_last);
+        if line.is_empty() {
+            continue;
         }
-        all_histories.push(histories);
+        histories.push((
+            i + 1,
+            line.split_whitespace()
+                .map(|s| s.parse::<i128>().unwrap())
+                .collect(),
+        ));
     }
-    all_histories
+    histories
 }
 
-fn part1(file: &str) -> i32 {
-    let mut sum: i32 = 0;
-    for mut histories in parse_histories(file) {
-        let rows = histories.len();
-        histories[rows - 1].push(0);
-        for i in (0..rows - 1).rev() {
-            let val = { histories[i + 1].last().unwrap() + histories[i].last().unwrap() };
-            histories[i].push(val);
-        }
-        sum += histories[0].last().unwrap();
+// sums the value at `index(history)` over every history, bailing out with
+// the offending line if one can't be extrapolated
+fn sum_extrapolated(file: &str, index: fn(&[i128]) -> i128) -> i128 {
+    let mut sum: i128 = 0;
+    for (line, history) in parse_histories(file) {
+        let value = Sequence::fit(&history)
+            .and_then(|sequence| sequence.at(index(&history)))
+            .unwrap_or_else(|e| {
+                eprintln!("can't extrapolate the history on line {}: {}", line, e);
+                process::exit(1);
+            });
+        sum += value;
     }
     sum
 }
 
-fn part2(file: &str) -> i32 {
-    let mut sum: i32 = 0;
-    for mut histories in parse_histories(file) {
-        let rows = histories.len();
-        histories[rows - 1].insert(0, 0);
-        for i in (0..rows - 1).rev() {
-            let val = { histories[i][0] - histories[i + 1][0] };
-            histories[i].insert(0, val);
+fn part1(file: &str) -> i128 {
+    sum_extrapolated(file, |history| history.len() as i128)
+}
+
+fn part2(file: &str) -> i128 {
+    sum_extrapolated(file, |_| -1)
+}
+
+// prints the polynomial behind each history and what comes next, which is
+// handy for poking at other polynomial-ish series
+fn analyse(file: &str) {
+    for (_, history) in parse_histories(file) {
+        match Sequence::fit(&history) {
+            Ok(sequence) => {
+                let terms: Vec<String> = sequence
+                    .coefficients()
+                    .unwrap()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| !c.is_zero())
+                    .map(|(power, c)| match power {
+                        0 => format!("{}", c),
+                        1 => format!("({})k", c),
+                        _ => format!("({})k^{}", c, power),
+                    })
+                    .collect();
+                println!(
+                    "degree {}: f(k) = {}, next {:?}, before {}",
+                    sequence.degree(),
+                    if terms.is_empty() {
+                        "0".to_string()
+                    } else {
+                        terms.join(" + ")
+                    },
+                    sequence.next_values(3).unwrap(),
+                    sequence.at(-1).unwrap()
+                );
+            }
+            Err(e) => println!("{:?}: {}", history, e),
         }
-        sum += histories[0][0];
     }
-    sum
 }
 
 // borrowed from https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html